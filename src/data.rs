@@ -1,21 +1,28 @@
 use std::{
-    collections::{ BTreeMap, BTreeSet },
+    collections::{ BTreeMap, BTreeSet, HashSet, VecDeque },
     error::Error,
     fmt::Display,
-    fs::File,
-    ops::{ Deref, DerefMut }, path::PathBuf,
+    fs,
+    ops::{ Deref, DerefMut }, path::{ Path, PathBuf },
+    str::FromStr,
 };
 
+use clap::ValueEnum;
+use serde::{ ser::{ Error as _, SerializeMap, SerializeStruct }, Deserialize, Deserializer, Serialize, Serializer };
+use strum::{ EnumIter, IntoEnumIterator };
+
 use crate::{
+    settings::{ indent, settings, ConnectorStyle },
     yaml::{
         Field,
+        UnityAsset,
         YamlError,
-        constrain_field_get_body
+        field_get_with
     },
-    field_get, field_get_body, field_value_type, impl_tryfrom_field
+    field_accessor, field_get, field_value_type, impl_tryfrom_field
 };
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, EnumIter, Serialize, Deserialize)]
 pub enum Effect {
     None,
     Chain,
@@ -46,9 +53,26 @@ impl_tryfrom_field!{Uint for Effect:
     }
 }
 
-impl Display for Effect {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", match self {
+impl Effect {
+    /// The id used for the effect in the asset files, the inverse of parsing it.
+    pub fn id(&self) -> u64 {
+        match self {
+            Effect::None => 0,
+            Effect::Chain => 1,
+            Effect::Inherit => 2,
+            Effect::Duplicate => 3,
+            Effect::Insert => 4,
+            Effect::Collapse => 5,
+            Effect::Redraw => 6,
+            Effect::ViewHand => 7,
+            Effect::Choose => 8,
+            Effect::Listen => 9,
+        }
+    }
+
+    /// The name the game uses for the effect.
+    pub fn name(&self) -> &'static str {
+        match self {
             Effect::None => "",
             Effect::Chain => "Chatter", 
             Effect::Inherit => "Elaborate",
@@ -59,11 +83,89 @@ impl Display for Effect {
             Effect::ViewHand => "Observe",
             Effect::Choose => "Prepare",
             Effect::Listen => "Listen",
-        })
+        }
+    }
+
+    /// The name shown for the effect, which for `Effect::None` is chosen with `--no-effect-label`.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Effect::None => settings().no_effect_label.text(),
+            effect => effect.name(),
+        }
+    }
+
+    /// A marker shown before the name when colour is enabled, to make effects easier to scan.
+    #[cfg(not(feature = "display_compat"))]
+    pub fn icon(&self) -> &'static str {
+        self.glyph()
+    }
+
+    /// A marker shown before the name when colour is enabled, to make effects easier to scan.
+    #[cfg(feature = "display_compat")]
+    pub fn icon(&self) -> &'static str {
+        self.letter()
+    }
+
+    /// The unicode marker for the effect.
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            Effect::None => "",
+            Effect::Chain => "»",
+            Effect::Inherit => "↳",
+            Effect::Duplicate => "⧉",
+            Effect::Insert => "↧",
+            Effect::Collapse => "↶",
+            Effect::Redraw => "↻",
+            Effect::ViewHand => "◉",
+            Effect::Choose => "✦",
+            Effect::Listen => "♪",
+        }
+    }
+
+    /// The ASCII marker used for the effect by `display_compat`.
+    pub fn letter(&self) -> &'static str {
+        match self {
+            Effect::None => "",
+            Effect::Chain => ">>",
+            Effect::Inherit => "->",
+            Effect::Duplicate => "x2",
+            Effect::Insert => "v",
+            Effect::Collapse => "<-",
+            Effect::Redraw => "~",
+            Effect::ViewHand => "o",
+            Effect::Choose => "*",
+            Effect::Listen => "?",
+        }
+    }
+
+    /// The ANSI SGR colour code used for the effect when colour is enabled.
+    fn color_code(&self) -> u8 {
+        match self {
+            Effect::None => 0,
+            Effect::Chain => 36,
+            Effect::Inherit => 32,
+            Effect::Duplicate => 35,
+            Effect::Insert => 34,
+            Effect::Collapse => 31,
+            Effect::Redraw => 33,
+            Effect::ViewHand => 96,
+            Effect::Choose => 95,
+            Effect::Listen => 92,
+        }
+    }
+}
+
+impl Display for Effect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if settings().color && *self != Effect::None {
+            write!(f, "\x1b[{}m{} {}\x1b[0m", self.color_code(), self.icon(), self.name())
+        } else {
+            write!(f, "{}", self.label())
+        }
     }
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, EnumIter, Serialize, Deserialize)]
 pub enum ConnectType {
     Circle,
     Triangle,
@@ -74,40 +176,124 @@ pub enum ConnectType {
 }
 
 impl Display for ConnectType {
-    #[cfg(not(feature = "display_compat"))]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        //std::fmt::Debug::fmt(&self, f)
+        write!(f, "{}", self.symbol(settings().connector_style))
+    }
+}
+
+impl ConnectType {
+    /// The symbol for the type in the given style.
+    pub fn symbol(&self, style: ConnectorStyle) -> &'static str {
+        match style {
+            ConnectorStyle::Unicode => self.glyph(),
+            ConnectorStyle::Letters => self.letter(),
+            ConnectorStyle::Ascii => self.ascii(),
+        }
+    }
+
+    /// The unicode symbol for the type.
+    pub fn glyph(&self) -> &'static str {
         use ConnectType::*;
-        write!(f, "{}", 
-            match self {
-                Circle => "○",
-                Triangle => "△",
-                Square => "□",
-                Diamond => "◊",
-                Dog => "🐾",
-                Spiral => "@",
-            }
-        )
+        match self {
+            Circle => "○",
+            Triangle => "△",
+            Square => "□",
+            Diamond => "◊",
+            Dog => "🐾",
+            Spiral => "@",
+        }
     }
 
-    #[cfg(feature = "display_compat")]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        //std::fmt::Debug::fmt(&self, f)
+    /// The ASCII letter used for the type by `display_compat`.
+    pub fn letter(&self) -> &'static str {
         use ConnectType::*;
-        write!(f, "{}", 
-            match self {
-                Circle => "C",
-                Triangle => "T",
-                Square => "S",
-                Diamond => "D",
-                Dog => "P",
-                Spiral => "@",
-            }
-        )
+        match self {
+            Circle => "C",
+            Triangle => "T",
+            Square => "S",
+            Diamond => "D",
+            Dog => "P",
+            Spiral => "@",
+        }
+    }
+
+    /// An ASCII drawing of the type, for terminals that can't show the symbols, which is easier
+    /// to tell apart than the letters.
+    pub fn ascii(&self) -> &'static str {
+        use ConnectType::*;
+        match self {
+            Circle => "(o)",
+            Triangle => "/_\\",
+            Square => "[]",
+            Diamond => "<>",
+            Dog => "paw",
+            Spiral => "@",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        use ConnectType::*;
+        match self {
+            Circle => "circle",
+            Triangle => "triangle",
+            Square => "square",
+            Diamond => "diamond",
+            Dog => "paw",
+            Spiral => "spiral",
+        }
+    }
+
+    /// The bit representing this type in a connector bitmask from the asset files.
+    fn bit(&self) -> u64 {
+        use ConnectType::*;
+        match self {
+            Circle => 0x1,
+            Triangle => 0x2,
+            Square => 0x4,
+            Diamond => 0x8,
+            Spiral => 0x10,
+            Dog => 0x20,
+        }
+    }
+
+    /// Read the types shown by `s` one after another, in any of the connector styles, e.g. `○△`,
+    /// `CT` or `(o) /_\`, so that whatever is shown can be typed back in. Letters can be either
+    /// case, and whitespace between symbols is ignored.
+    pub fn parse_symbols(s: &str) -> Result<Vec<Self>, String> {
+        let mut types = Vec::new();
+        let mut rest = s.trim_start();
+        while let Some(next) = rest.chars().next() {
+            // The longest match wins, so that `paw` isn't read as the letter `P`
+            let found = ConnectType::iter()
+                .flat_map(|connect_type| ConnectorStyle::value_variants().iter()
+                    .map(move |style| (connect_type.clone(), connect_type.symbol(*style))))
+                .filter(|(_, symbol)| rest.get(..symbol.len()).is_some_and(|start| start.eq_ignore_ascii_case(symbol)))
+                .max_by_key(|(_, symbol)| symbol.len());
+            let Some((connect_type, symbol)) = found else {
+                return Err(format!("Unknown connector symbol `{}`", next));
+            };
+            types.push(connect_type);
+            rest = rest[symbol.len()..].trim_start();
+        }
+        Ok(types)
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl FromStr for ConnectType {
+    type Err = String;
+
+    /// Parse a single connector symbol, e.g. "@", "P" or "paw".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match ConnectType::parse_symbols(s)?.as_slice() {
+            [connect_type] => Ok(connect_type.clone()),
+            _ => Err(format!("`{}` isn't a single connector symbol", s)),
+        }
+    }
+}
+
+/// The connector types on one side of a card. The asset files store these as a bitmask with one
+/// bit per type, so a card can't have two of the same type, and a set loses nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
 pub struct Connector(BTreeSet<ConnectType>);
 
 impl Deref for Connector {
@@ -126,55 +312,180 @@ impl DerefMut for Connector {
 
 impl Display for Connector {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for c in self.iter() {
-            write!(f, "{}", c)?;
+        let mut types = self.sorted_types();
+        if let Some(order) = &settings().connector_order {
+            types.sort_by_key(|connect_type| order.0.iter().position(|t| t == connect_type));
         }
-        Ok(())
+        // The ASCII drawings run together without a space between them
+        let sep = if settings().connector_style == ConnectorStyle::Ascii { " " } else { "" };
+        write_vec_sep(&types, sep, f)
     }
 }
 
-impl_tryfrom_field!{Uint for Connector:
-    |connect| {
-        let mut set = BTreeSet::new();
+/// The order to show the types within a connector in, instead of the order of `ConnectType`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectorOrder(Vec<ConnectType>);
 
-        if (connect & 0x1) > 0 {
-            set.insert(ConnectType::Circle);
-        }
-        if (connect & 0x2) > 0 {
-            set.insert(ConnectType::Triangle);
-        }
-        if (connect & 0x4) > 0 {
-            set.insert(ConnectType::Square);
-        }
-        if (connect & 0x8) > 0 {
-            set.insert(ConnectType::Diamond);
-        }
-        if (connect & 0x10) > 0 {
-            set.insert(ConnectType::Spiral);
-        }
-        if (connect & 0x20) > 0 {
-            set.insert(ConnectType::Dog);
+impl FromStr for ConnectorOrder {
+    type Err = String;
+
+    /// Parse an order such as "@○△□◊🐾". Types that aren't listed are shown after the listed ones,
+    /// in their usual order.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut order = Vec::new();
+        for connect_type in ConnectType::parse_symbols(s)? {
+            if order.contains(&connect_type) {
+                return Err(format!("Connector symbol `{}` is listed more than once", connect_type.glyph()));
+            }
+            order.push(connect_type);
         }
+        order.extend(ConnectType::iter().filter(|connect_type| !order.contains(connect_type)).collect::<Vec<_>>());
+        Ok(Self(order))
+    }
+}
+
+impl FromStr for Connector {
+    type Err = String;
+
+    /// Parse a connector from its displayed form in any style, e.g. "○△", "CT" or "(o) /_\".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(ConnectType::parse_symbols(s)?.into_iter().collect()))
+    }
+}
+
+impl Serialize for Connector {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.sorted_types().serialize(serializer)
+    }
+}
+
+impl Connector {
+    /// The types in the connector in their canonical order: Circle, Triangle, Square, Diamond,
+    /// Dog, Spiral, i.e. the order `ConnectType` is declared in. This is the order used for
+    /// exports, so that they are stable between runs.
+    pub fn sorted_types(&self) -> Vec<ConnectType> {
+        // `BTreeSet` already iterates in `Ord` order, which is derived from the declaration order
+        self.iter().cloned().collect()
+    }
+
+    /// Whether a card with this connector as its input can be played after a card with `other`
+    /// as its output, i.e. whether they share at least one type.
+    pub fn matches(&self, other: &Connector) -> bool {
+        !self.is_disjoint(other)
+    }
+
+    /// Like `matches`, but a connector containing `wildcard` matches any other connector.
+    pub fn matches_with_wildcard(&self, other: &Connector, wildcard: &ConnectType) -> bool {
+        self.contains(wildcard) || other.contains(wildcard) || self.matches(other)
+    }
+
+    /// Encode the connector as the bitmask used in the asset files.
+    pub fn to_bits(&self) -> u64 {
+        self.iter().fold(0, |bits, connect_type| bits | connect_type.bit())
+    }
+}
 
-        Ok(Self(set))
+impl_tryfrom_field!{Uint for Connector:
+    |connect| {
+        // Anything outside the known bits would be lost, such as a way of marking doubles
+        if connect & !0x3f != 0 {
+            log::warn!("Connector value {:#x} has bits that aren't a known symbol; only {:#x} is shown", connect, connect & 0x3f);
+        }
+        let connector = Self(ConnectType::iter()
+            .filter(|connect_type| (connect & connect_type.bit()) > 0)
+            .collect());
+        Ok(connector)
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
 pub struct Card {
     input: Connector,
     output: Connector,
     effect: Effect,
+    /// Any entries in the card's asset data besides `input`, `output` and `effect`, which might be
+    /// parameters for the effect. Usually empty.
+    #[serde(default)]
+    extra: BTreeMap<String, Field>,
+}
+
+impl Card {
+    pub fn new(input: Connector, output: Connector, effect: Effect) -> Self {
+        Self { input, output, effect, extra: BTreeMap::new() }
+    }
+
+    pub fn input(&self) -> &Connector {
+        &self.input
+    }
+
+    pub fn output(&self) -> &Connector {
+        &self.output
+    }
+
+    pub fn effect(&self) -> &Effect {
+        &self.effect
+    }
+
+    /// The `(input, output, effect)` values used for the card in the asset files.
+    pub fn to_raw(&self) -> (u64, u64, u64) {
+        (self.input.to_bits(), self.output.to_bits(), self.effect.id())
+    }
+
+    /// The number of types in the card's input and output together.
+    pub fn total_connector_count(&self) -> usize {
+        self.input.len() + self.output.len()
+    }
+}
+
+/// A set of optional conditions for finding cards. Conditions left as `None` match any card.
+#[derive(Debug, Default)]
+pub struct CardQuery {
+    /// The card's input must include all of these types.
+    pub input: Option<Connector>,
+    /// The card's output must include all of these types.
+    pub output: Option<Connector>,
+    pub effect: Option<Effect>,
+}
+
+impl CardQuery {
+    pub fn matches(&self, card: &Card) -> bool {
+        self.input.as_ref().is_none_or(|input| card.input.is_superset(input))
+            && self.output.as_ref().is_none_or(|output| card.output.is_superset(output))
+            && self.effect.as_ref().is_none_or(|effect| card.effect == *effect)
+    }
+}
+
+impl Serialize for Card {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Leave out `extra` when it's empty, except in formats like `--save-cache` that rely on
+        // every field being there
+        let skip_extra = serializer.is_human_readable() && self.extra.is_empty();
+        let mut card = serializer.serialize_struct("Card", if skip_extra { 3 } else { 4 })?;
+        card.serialize_field("input", &self.input)?;
+        card.serialize_field("output", &self.output)?;
+        card.serialize_field("effect", &self.effect)?;
+        if skip_extra {
+            card.skip_field("extra")?;
+        } else {
+            card.serialize_field("extra", &self.extra)?;
+        }
+        card.end()
+    }
 }
 
 impl Display for Card {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} | {}", self.input, self.output)?;
-        if self.effect != Effect::None {
-            write!(f, " + {}", self.effect)
-        } else {
-            Ok(())
+        if !self.effect.label().is_empty() {
+            write!(f, " + {}", self.effect)?;
         }
+        if !self.extra.is_empty() {
+            let extra: Vec<String> = self.extra.iter()
+                .map(|(key, value)| format!("{}: {}", key, serde_json::to_string(value).unwrap_or_else(|_| format!("{:?}", value))))
+                .collect();
+            write!(f, " [{}]", extra.join(", "))?;
+        }
+        Ok(())
     }
 }
 
@@ -202,20 +513,43 @@ impl_tryfrom_field!{Struct for Card:
                             return Err("No `effect` field in card".into());
                         }
                     )?,
+            extra: value.iter()
+                .filter(|(key, _)| !["input", "output", "effect"].contains(&key.as_str()))
+                .map(|(key, field)| (key.clone(), field.clone()))
+                .collect(),
         })
     }
 }
 
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct RawEvent {
     pub id: String,
     pub npc_guid: String,
     pub sequence_count: u8,
     pub strike_count: u8,
     pub sequence_lengths: Vec<u8>,
-    pub deck: Option<Deck>
+    pub deck: Option<Deck>,
+    /// The `sequence` field as it appears in the asset, before `sequence_lengths` is parsed from it.
+    /// Only kept for debugging, so it's left out of comparisons and exports.
+    #[serde(skip)]
+    pub raw_sequence: String,
+    /// The `overrideDeck` field as it appears in the asset.
+    #[serde(skip)]
+    pub raw_override_deck: u64,
+}
+
+impl PartialEq for RawEvent {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.npc_guid == other.npc_guid
+            && self.sequence_count == other.sequence_count
+            && self.strike_count == other.strike_count
+            && self.sequence_lengths == other.sequence_lengths
+            && self.deck == other.deck
+    }
 }
+impl Eq for RawEvent {}
 
 impl PartialOrd for RawEvent {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
@@ -229,45 +563,343 @@ impl Ord for RawEvent {
     }
 }
 
+impl RawEvent {
+    /// The number of cards needed to complete every sequence in the event.
+    pub fn total_sequence_length(&self) -> u32 {
+        self.sequence_lengths.iter().map(|&len| len as u32).sum()
+    }
+
+    /// A rough measure of how hard the event is:
+    ///
+    /// `3 * sequence_count + total_sequence_length - 2 * strike_count`, floored at 0.
+    ///
+    /// Every concord and every card in a sequence makes the event longer, while every
+    /// discord the player is allowed makes it more forgiving.
+    pub fn difficulty(&self) -> u32 {
+        (3 * self.sequence_count as u32 + self.total_sequence_length())
+            .saturating_sub(2 * self.strike_count as u32)
+    }
+
+    /// A line for each difference from `older`, such as `strike_count: 1 -> 2` or
+    /// `deck: + ○ | △`.
+    pub fn describe_changes(&self, older: &RawEvent) -> Vec<String> {
+        let lengths = |event: &RawEvent| event.sequence_lengths.iter().map(u8::to_string).collect::<Vec<_>>().join(", ");
+        let mut changes = Vec::new();
+        for (name, old, new) in [
+            ("npc_guid", older.npc_guid.clone(), self.npc_guid.clone()),
+            ("sequence_count", older.sequence_count.to_string(), self.sequence_count.to_string()),
+            ("strike_count", older.strike_count.to_string(), self.strike_count.to_string()),
+            ("sequence_lengths", lengths(older), lengths(self)),
+        ] {
+            if old != new {
+                changes.push(format!("{}: {} -> {}", name, old, new));
+            }
+        }
+        match (&older.deck, &self.deck) {
+            (Some(old), Some(new)) => changes.extend(new.describe_changes(old).into_iter().map(|change| format!("deck: {}", change))),
+            (None, Some(_)) => changes.push("now overrides the NPC's deck".to_owned()),
+            (Some(_), None) => changes.push("no longer overrides the NPC's deck".to_owned()),
+            (None, None) => {},
+        }
+        changes
+    }
+
+    /// Check the event for values that parse fine but probably aren't intended.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if self.sequence_count == 0 && self.deck.is_some() {
+            issues.push(ValidationIssue::warning(IssueCategory::Consistency, &self.id, "overrides the deck but has no sequences to play"));
+        }
+        issues
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueCategory {
+    /// Something refers to data that doesn't exist.
+    Reference,
+    /// The same id is used more than once.
+    Duplicate,
+    /// Values that disagree with each other.
+    Consistency,
+    /// A card whose effect breaks one of the `EffectRule`s.
+    Effect,
+}
+
+impl Display for IssueCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            IssueCategory::Reference => "reference",
+            IssueCategory::Duplicate => "duplicate",
+            IssueCategory::Consistency => "consistency",
+            IssueCategory::Effect => "effect",
+        })
+    }
+}
+
+/// A problem found in otherwise loadable data.
+#[derive(Debug, Serialize)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub category: IssueCategory,
+    /// The id of the event or NPC the issue is about, or the path of the asset.
+    pub entity_id: String,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    pub fn warning(category: IssueCategory, entity_id: &str, message: &str) -> Self {
+        Self { severity: Severity::Warning, category, entity_id: entity_id.to_owned(), message: message.to_owned() }
+    }
+
+    pub fn error(category: IssueCategory, entity_id: &str, message: &str) -> Self {
+        Self { severity: Severity::Error, category, entity_id: entity_id.to_owned(), message: message.to_owned() }
+    }
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} [{}]: {}: {}", match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }, self.category, self.entity_id, self.message)
+    }
+}
+
+/// A heuristic for spotting authoring mistakes: cards with `effect` should have between the given
+/// numbers of connector types in their input and output. Rules can be loaded from a yaml or json
+/// list with `--effect-rules`, e.g.
+///
+/// ```yaml
+/// - effect: Accommodate
+///   min_outputs: 2
+///   reason: the game only uses Accommodate on doubles
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct EffectRule {
+    /// The name the game uses for the effect, e.g. `Chatter`.
+    #[serde(deserialize_with = "deserialize_effect_name")]
+    pub effect: Effect,
+    #[serde(default)]
+    pub min_inputs: Option<usize>,
+    #[serde(default)]
+    pub max_inputs: Option<usize>,
+    #[serde(default)]
+    pub min_outputs: Option<usize>,
+    #[serde(default)]
+    pub max_outputs: Option<usize>,
+    /// Why cards with the effect should look like this, shown with each card that doesn't.
+    pub reason: String,
+}
+
+fn deserialize_effect_name<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Effect, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    Effect::iter()
+        .find(|effect| *effect != Effect::None && effect.name().eq_ignore_ascii_case(&name))
+        .ok_or_else(|| serde::de::Error::custom(format!("unknown effect `{}`", name)))
+}
+
+impl EffectRule {
+    /// The rules used unless `--effect-rules` is given. These only catch effects that have nothing
+    /// to act on:
+    ///
+    /// - Chatter and Elaborate carry on from the card's output, so it shouldn't be empty.
+    /// - Backtrack returns to the card that was played before, so the card should have an input
+    ///   to follow it with.
+    pub fn defaults() -> Vec<EffectRule> {
+        let rule = |effect, min_inputs, min_outputs, reason: &str| EffectRule {
+            effect,
+            min_inputs,
+            max_inputs: None,
+            min_outputs,
+            max_outputs: None,
+            reason: reason.to_owned(),
+        };
+        vec![
+            rule(Effect::Chain, None, Some(1), "Chatter carries on from the card's output"),
+            rule(Effect::Inherit, None, Some(1), "Elaborate carries on from the card's output"),
+            rule(Effect::Collapse, Some(1), None, "Backtrack has to follow the card played before it"),
+        ]
+    }
+
+    /// Whether `card` has the rule's effect but not the connectors it calls for.
+    pub fn is_broken_by(&self, card: &Card) -> bool {
+        let outside = |count: usize, min: Option<usize>, max: Option<usize>| {
+            min.is_some_and(|min| count < min) || max.is_some_and(|max| count > max)
+        };
+        card.effect == self.effect
+            && (outside(card.input.len(), self.min_inputs, self.max_inputs)
+                || outside(card.output.len(), self.min_outputs, self.max_outputs))
+    }
+}
+
+/// Read a list of `EffectRule`s from a yaml or json file.
+pub fn load_effect_rules(path: &Path) -> Result<Vec<EffectRule>, Box<dyn Error>> {
+    Ok(serde_yaml::from_str(&fs::read_to_string(path)?)?)
+}
+
 impl_tryfrom_field!{Struct for RawEvent:
     |event| {
         field_get!(let id: Str = event.id);
-        field_get!(let sequence: Str = event.sequence);
         field_get!(event id, let seq_count: Uint = event.sequenceCount);
         field_get!(event id, let strike_count: Uint = event.strikeCount);
         field_get!(event id, let override_deck: Uint = event.overrideDeck);
         field_get!(event id, let npc_data: Struct = event.npc);
         field_get!(event id, let npc_guid: Str = npc_data.guid);
 
-        let sequence_lengths: Vec<u8> = sequence.split("")
-                    .skip(2)
-                    .step_by(8)
-                    .flat_map(|s| {
-                        u8::from_str_radix(s, 10)
-                    })
-                    .collect();
+        // Older assets pack the lengths into a string, newer ones list them directly
+        let Some(sequence) = event.get("sequence") else {
+            return Err(YamlError::MissingField { entity: Some(id.to_owned()), key: "sequence".to_owned() });
+        };
+        let (sequence_lengths, raw_sequence) = if let Some(sequence) = sequence.as_str() {
+            (parse_sequence(id, sequence)?, sequence.to_owned())
+        } else if let Some(sequence) = sequence.as_list() {
+            let lengths = sequence.iter()
+                .map(|length| match length.as_uint() {
+                    Some(length) => Ok(u8::try_from(length)?),
+                    None => Err(YamlError::WrongType { entity: Some(id.to_owned()), key: "sequence".to_owned(), expected: "List of Uint" }),
+                })
+                .collect::<Result<Vec<u8>, YamlError>>()?;
+            let raw = lengths.iter().map(u8::to_string).collect::<Vec<_>>().join(", ");
+            (lengths, format!("[{}]", raw))
+        } else {
+            return Err(YamlError::WrongType { entity: Some(id.to_owned()), key: "sequence".to_owned(), expected: "Str or List" });
+        };
 
-        if sequence_lengths.len() as u64 != *seq_count {
+        if sequence_lengths.len() as u64 != seq_count {
             return Err(format!("{}: Failed to parse `sequence` field.", id).into());
         }
 
         Ok(Self {
-            id: id.clone(),
-            npc_guid: npc_guid.clone(),
-            sequence_count: u8::try_from(*seq_count)?,
-            strike_count: u8::try_from(*strike_count)?,
+            id: id.to_owned(),
+            npc_guid: npc_guid.to_owned(),
+            sequence_count: u8::try_from(seq_count)?,
+            strike_count: u8::try_from(strike_count)?,
             sequence_lengths,
-            deck: if *override_deck == 1u64 {
+            deck: if override_deck == 1u64 {
                 field_get!(event id, let event_deck: Struct = event.deck);
                 Some(event_deck.try_into()?)
             } else {
                 None
-            }
+            },
+            raw_sequence,
+            raw_override_deck: override_deck,
         })
     }
 }
 
-pub fn write_vec_sep<T: Display>(v: &Vec<T>, sep: &str, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// Pull the lengths out of an event's packed `sequence` string, where each length is a 32 bit
+/// little-endian integer written as 8 hex digits, e.g. `03000000` for 3 or `0a000000` for 10.
+/// Chunks that don't parse are skipped, unless the `--strict-sequence` option is set, in which
+/// case they are an error.
+/// Control characters, such as stray `\r`s from CRLF line endings, are dropped first so they
+/// can't shift the chunks.
+fn parse_sequence(id: &str, sequence: &str) -> Result<Vec<u8>, YamlError> {
+    let strict = settings().strict_sequence;
+    let chars: Vec<char> = sequence.chars().filter(|c| !c.is_control()).collect();
+    let mut lengths = Vec::new();
+    for (index, chunk) in chars.chunks(8).enumerate() {
+        match parse_packed_length(chunk) {
+            Some(length) => lengths.push(length),
+            None if strict => {
+                return Err(format!("event {}: Couldn't parse `{}` (at offset {}) in `sequence` field.",
+                                   id, chunk.iter().collect::<String>(), index * 8).into());
+            },
+            None => {},
+        }
+    }
+    Ok(lengths)
+}
+
+/// Read one 8 hex digit chunk of a packed `sequence`, or `None` if it isn't one or is too large
+/// to be a length.
+fn parse_packed_length(chunk: &[char]) -> Option<u8> {
+    if chunk.len() != 8 || !chunk.iter().all(char::is_ascii_hexdigit) {
+        return None;
+    }
+    let value = u32::from_str_radix(&chunk.iter().collect::<String>(), 16).ok()?;
+    u8::try_from(value.swap_bytes()).ok()
+}
+
+/// A pattern for an event's `sequence_lengths`, such as `3,4,5`. `*` matches any length, and a
+/// trailing `...` matches any number of further lengths, e.g. `3,*,...`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequencePattern {
+    /// The length needed at each position, or `None` for any.
+    lengths: Vec<Option<u8>>,
+    /// Whether the event may have more lengths after the ones in the pattern.
+    prefix: bool,
+}
+
+impl SequencePattern {
+    pub fn matches(&self, sequence_lengths: &[u8]) -> bool {
+        let length_ok = if self.prefix {
+            sequence_lengths.len() >= self.lengths.len()
+        } else {
+            sequence_lengths.len() == self.lengths.len()
+        };
+        length_ok && self.lengths.iter()
+            .zip(sequence_lengths)
+            .all(|(wanted, length)| wanted.is_none_or(|wanted| wanted == *length))
+    }
+}
+
+impl FromStr for SequencePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        let prefix = parts.last() == Some(&"...");
+        if prefix {
+            parts.pop();
+        }
+        let lengths = parts.into_iter()
+            .filter(|part| !part.is_empty())
+            .map(|part| match part {
+                "*" => Ok(None),
+                "..." => Err("`...` can only come at the end of the pattern".to_owned()),
+                part => part.parse().map(Some).map_err(|_| format!("`{}` isn't a sequence length or `*`", part)),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { lengths, prefix })
+    }
+}
+
+/// The fields of an exported event that `--fields` can choose from, in their usual order.
+/// `npc_id` isn't part of `RawEvent`, but is added when exporting.
+pub const EVENT_FIELDS: [&str; 7] = ["id", "npc_id", "npc_guid", "sequence_count", "strike_count", "sequence_lengths", "deck"];
+
+/// The event fields to export, from a comma separated list such as `id,npc_id,sequence_count`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventFields(pub Vec<String>);
+
+impl FromStr for EventFields {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = Vec::new();
+        for field in s.split(',').map(str::trim).filter(|field| !field.is_empty()) {
+            if !EVENT_FIELDS.contains(&field) {
+                return Err(format!("Unknown field `{}`; use {}", field, EVENT_FIELDS.join(", ")));
+            }
+            fields.push(field.to_owned());
+        }
+        if fields.is_empty() {
+            return Err(format!("No fields given; use {}", EVENT_FIELDS.join(", ")));
+        }
+        Ok(Self(fields))
+    }
+}
+
+pub fn write_vec_sep<T: Display>(v: &[T], sep: &str, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     let mut iter = v.iter();
     while let Some(el) = iter.next() {
         write!(f, "{}", el)?;
@@ -280,27 +912,387 @@ pub fn write_vec_sep<T: Display>(v: &Vec<T>, sep: &str, f: &mut std::fmt::Format
 
 impl Display for RawEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (i1, i2) = (indent(1), indent(2));
         writeln!(f, "{}:", self.id)?;
-        writeln!(f, "\tnpc_guid: {}", self.npc_guid)?;
-        writeln!(f, "\tsequence_count: {}", self.sequence_count)?;
-        writeln!(f, "\tstrike_count: {}", self.strike_count)?;
-        write!(f, "\tsequence_lengths: ")?;
+        writeln!(f, "{i1}npc_guid: {}", self.npc_guid)?;
+        writeln!(f, "{i1}sequence_count: {}", self.sequence_count)?;
+        writeln!(f, "{i1}strike_count: {}", self.strike_count)?;
+        writeln!(f, "{i1}difficulty: {}", self.difficulty())?;
+        write!(f, "{i1}sequence_lengths: ")?;
         write_vec_sep(&self.sequence_lengths, ", ", f)?;
-        writeln!(f, "\n\tdeck:")?;
+        writeln!(f)?;
         if let Some(deck) = &self.deck {
-            writeln!(f, "{}", deck)
-        } else {
-            writeln!(f, "\t\tDefault for cycle; see character with npc guid `{}`", self.npc_guid)
+            writeln!(f, "{i1}deck:")?;
+            writeln!(f, "{}", deck)?;
+        } else if settings().show_inheritance_note {
+            writeln!(f, "{i1}deck:")?;
+            writeln!(f, "{i2}Default for cycle; see character with npc guid `{}`", self.npc_guid)?;
+        }
+        if settings().show_raw_fields {
+            writeln!(f, "{i1}raw sequence: {}", self.raw_sequence)?;
+            writeln!(f, "{i1}raw overrideDeck: {}", self.raw_override_deck)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse the contents of an `event_data.asset` file, stopping at the first event that fails.
+pub fn parse_asset(contents: &str) -> Result<Vec<RawEvent>, YamlError> {
+    parse_asset_entries(UnityAsset::parse(contents)?)?.into_iter().collect()
+}
+
+/// Parse the events in an already loaded `event_data.asset` file, giving the result for each
+/// event separately.
+pub fn parse_asset_entries(asset: UnityAsset) -> Result<Vec<Result<RawEvent, YamlError>>, YamlError> {
+    let Field::Struct(data_map) = asset.root else { return Err("Root isn't a map".into()); };
+    let ref_data_map = &data_map;
+    field_get!(let monobehaviour: Struct = ref_data_map.MonoBehaviour);
+    field_get!(let events: List = monobehaviour.data);
+    Ok(events.iter().map(RawEvent::try_from).collect())
+}
+
+/// Read back events previously written by `--list --format json`.
+pub fn events_from_json(contents: &str) -> Result<Vec<RawEvent>, serde_json::Error> {
+    serde_json::from_str(contents)
+}
+
+/// Parse the contents of an NPC `.asset` file, or `None` if the asset isn't an NPC.
+/// Decks that are stored in a separate asset are looked up with `resolve`, which is given the
+/// guid of the asset and returns the fields of its `MonoBehaviour`.
+pub fn parse_npc(contents: &str, resolve: &GuidResolver) -> Result<Option<NPC>, Box<dyn Error + Send + Sync>> {
+    let asset = UnityAsset::parse(contents)?;
+    let class_id = asset.class_id;
+    let mut monobehaviour = asset.into_monobehaviour()?;
+    if NPC::is_npc(&monobehaviour) {
+        resolve_deck_references(&mut monobehaviour, resolve)?;
+        let mut npc: NPC = (&monobehaviour).try_into()?;
+        npc.class_id = class_id;
+        Ok(Some(npc))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Looks up the `MonoBehaviour` fields of the asset with the given guid.
+pub type GuidResolver<'a> = dyn Fn(&str) -> Result<Field, Box<dyn Error + Send + Sync>> + Sync + 'a;
+
+/// Replace `deckN` fields that refer to another asset, e.g. `{fileID: 11400000, guid: ..., type: 2}`,
+/// with the contents of that asset. Other references, such as `m_Script` or a field that only starts
+/// with `deck`, are left alone.
+fn resolve_deck_references(npc: &mut BTreeMap<String, Field>, resolve: &GuidResolver) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let is_deck = |key: &str| key.strip_prefix("deck")
+        .is_some_and(|cycle| !cycle.is_empty() && cycle.bytes().all(|byte| byte.is_ascii_digit()));
+    for (key, value) in npc.iter_mut().filter(|(key, _)| is_deck(key)) {
+        if value.get("cards").is_some() {
+            continue;
         }
+        let Some(guid) = value.get("guid").and_then(Field::as_str) else { continue; };
+        let guid = guid.to_owned();
+        *value = resolve(&guid)
+            .map_err(|err| format!("`{}` refers to the asset with guid `{}`, but {}", key, guid, err))?;
     }
+    Ok(())
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Deck {
-    pub anchor: Card,
+    /// Most decks have an anchor, but some (possibly tutorial decks) don't.
+    pub anchor: Option<Card>,
     pub cards: Vec<Card>
 }
 
+impl Deck {
+    /// Every card in the deck: the anchor, if there is one, followed by the other cards.
+    /// Use `cards` for just the non-anchor cards.
+    pub fn all_cards(&self) -> impl Iterator<Item = &Card> {
+        self.anchor.iter().chain(&self.cards)
+    }
+
+    /// The number of cards in the deck, including the anchor.
+    pub fn card_count(&self) -> usize {
+        self.cards.len() + usize::from(self.anchor.is_some())
+    }
+
+    /// A warning for each different card in the deck that breaks one of `rules`, where `location`
+    /// says which deck this is.
+    pub fn effect_rule_issues(&self, location: &str, rules: &[EffectRule]) -> Vec<ValidationIssue> {
+        let cards: BTreeSet<&Card> = self.all_cards().collect();
+        cards.into_iter()
+            .flat_map(|card| rules.iter()
+                .filter(|rule| rule.is_broken_by(card))
+                .map(move |rule| ValidationIssue::warning(IssueCategory::Effect, location, &format!("card `{}`: {}", card, rule.reason))))
+            .collect()
+    }
+
+    /// The non-anchor cards `(added, removed)` in this deck compared to `older`.
+    /// Decks are treated as multisets, so a card that appears twice here but only once in `older`
+    /// is reported as added once.
+    pub fn diff<'a>(&'a self, older: &'a Deck) -> DeckDiff<'a> {
+        let mut removed: Vec<&Card> = older.cards.iter().collect();
+        let mut added = Vec::new();
+        for card in &self.cards {
+            if let Some(pos) = removed.iter().position(|c| *c == card) {
+                removed.swap_remove(pos);
+            } else {
+                added.push(card);
+            }
+        }
+        (added, removed)
+    }
+
+    /// A line for each difference from `older`: the anchor, then added and removed cards.
+    pub fn describe_changes(&self, older: &Deck) -> Vec<String> {
+        self.describe_diff(older, &self.diff(older))
+    }
+
+    /// The lines `describe_changes` gives, for cards `(added, removed)` already found by `diff`.
+    pub fn describe_diff(&self, older: &Deck, (added, removed): &DeckDiff) -> Vec<String> {
+        let mut changes = Vec::new();
+        if self.anchor != older.anchor {
+            changes.push(format!("anchor: {} -> {}", describe_anchor(&older.anchor), describe_anchor(&self.anchor)));
+        }
+        changes.extend(added.iter().map(|card| format!("+ {}", card)));
+        changes.extend(removed.iter().map(|card| format!("- {}", card)));
+        changes
+    }
+
+    /// How many times each non-anchor card appears in the deck. The anchor is left out, since it
+    /// is always drawn first rather than being part of the pool.
+    pub fn card_multiplicities(&self) -> BTreeMap<Card, usize> {
+        let mut counts = BTreeMap::new();
+        for card in &self.cards {
+            *counts.entry(card.clone()).or_default() += 1;
+        }
+        counts
+    }
+
+    /// The total number of connector types on every card, including the anchor, as a rough
+    /// measure of how complex the deck is.
+    pub fn total_weight(&self) -> usize {
+        self.all_cards().map(Card::total_connector_count).sum()
+    }
+
+    /// The raw `(input, output, effect)` values of every card, anchor first if there is one, as
+    /// they would be written in an asset file.
+    pub fn to_raw_fields(&self) -> Vec<(u64, u64, u64)> {
+        self.all_cards()
+            .map(Card::to_raw)
+            .collect()
+    }
+
+    /// The deck in the YAML form used by the asset files, so it can be pasted back into one.
+    pub fn to_asset_yaml(&self) -> String {
+        let format_card = |(input, output, effect)| format!("{{input: {}, output: {}, effect: {}}}", input, output, effect);
+        let mut raw = self.to_raw_fields().into_iter();
+        let mut yaml = String::new();
+        if self.anchor.is_some() {
+            yaml.push_str(&format!("anchor: {}\n", format_card(raw.next().expect("anchor is first"))));
+        }
+        yaml.push_str("cards:");
+        if self.cards.is_empty() {
+            yaml.push_str(" []");
+        }
+        for card in raw {
+            yaml.push_str(&format!("\n- {}", format_card(card)));
+        }
+        yaml
+    }
+
+    /// The cards that can start a sequence: the anchor, plus any card with an empty input, since
+    /// an empty input never has to match the output of a card before it.
+    pub fn entry_points(&self) -> Vec<&Card> {
+        self.anchor.iter()
+            .chain(self.cards.iter().filter(|card| card.input.is_empty()))
+            .collect()
+    }
+
+    /// The non-anchor cards that could be played after a card whose output is `current`.
+    pub fn playable_after(&self, current: &Connector) -> Vec<&Card> {
+        self.cards.iter()
+            .filter(|card| can_follow(current, card))
+            .collect()
+    }
+
+    /// The shortest chain of non-anchor cards that can be played one after another, starting after
+    /// a card whose output is `from`, that ends with a card whose output has every type in `to`.
+    /// Each card is played at most once. Empty if `from` already has every type in `to`, and
+    /// `None` if the deck can't get there.
+    pub fn path_between(&self, from: &Connector, to: &Connector) -> Option<Vec<&Card>> {
+        if to.is_subset(from) {
+            return Some(Vec::new());
+        }
+        // For each card reached, the card played before it, or `None` if it was played after `from`
+        let mut previous: BTreeMap<usize, Option<usize>> = BTreeMap::new();
+        let mut queue = VecDeque::new();
+        for (index, card) in self.cards.iter().enumerate() {
+            if can_follow(from, card) {
+                previous.insert(index, None);
+                queue.push_back(index);
+            }
+        }
+        while let Some(index) = queue.pop_front() {
+            if to.is_subset(&self.cards[index].output) {
+                let mut path = vec![&self.cards[index]];
+                let mut current = index;
+                while let Some(before) = previous[&current] {
+                    path.push(&self.cards[before]);
+                    current = before;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (next, card) in self.cards.iter().enumerate() {
+                if !previous.contains_key(&next) && can_follow(&self.cards[index].output, card) {
+                    previous.insert(next, Some(index));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// A chain of `length` different non-anchor cards that can be played one after another after the
+    /// anchor, or starting with any card if there's no anchor. `None` if the deck has no such chain.
+    pub fn chain_of_length(&self, length: usize) -> Option<Vec<&Card>> {
+        /// Positions the search has already failed from: the last card played, or `None` for the
+        /// start, and how many of each card were left.
+        type DeadEnds = HashSet<(Option<usize>, Vec<usize>)>;
+
+        fn extend(cards: &[&Card], start: Option<&Connector>, length: usize, left: &mut Vec<usize>, chain: &mut Vec<usize>, dead_ends: &mut DeadEnds) -> bool {
+            if chain.len() == length {
+                return true;
+            }
+            let position = (chain.last().copied(), left.clone());
+            if dead_ends.contains(&position) {
+                return false;
+            }
+            let previous = match chain.last() {
+                Some(&index) => Some(&cards[index].output),
+                None => start,
+            };
+            for (index, card) in cards.iter().enumerate() {
+                if left[index] == 0 || previous.is_some_and(|previous| !can_follow(previous, card)) {
+                    continue;
+                }
+                left[index] -= 1;
+                chain.push(index);
+                if extend(cards, start, length, left, chain, dead_ends) {
+                    return true;
+                }
+                chain.pop();
+                left[index] += 1;
+            }
+            dead_ends.insert(position);
+            false
+        }
+
+        // Copies of a card are interchangeable, so the search is over how many of each card are
+        // left rather than which copies, and positions that fail are only searched once
+        let mut cards: Vec<&Card> = Vec::new();
+        let mut left = Vec::new();
+        for card in &self.cards {
+            match cards.iter().position(|other| *other == card) {
+                Some(index) => left[index] += 1,
+                None => {
+                    cards.push(card);
+                    left.push(1);
+                },
+            }
+        }
+        let mut chain = Vec::new();
+        extend(&cards, self.anchor.as_ref().map(|anchor| &anchor.output), length, &mut left, &mut chain, &mut HashSet::new())
+            .then(|| chain.into_iter().map(|index| cards[index]).collect())
+    }
+
+    /// One line per sequence length, showing a chain of cards from this deck that fills it, e.g.
+    /// `Segment 1 (len 3): ○ | △ → △ | □ → □ | ◊`.
+    pub fn segment_plan(&self, sequence_lengths: &[u8]) -> Vec<String> {
+        sequence_lengths.iter().enumerate()
+            .map(|(index, &length)| {
+                let chain = match self.chain_of_length(length as usize) {
+                    Some(chain) => chain.into_iter().map(Card::to_string).collect::<Vec<_>>().join(" → "),
+                    None => format!("no chain of {} cards in this deck", length),
+                };
+                format!("Segment {} (len {}): {}", index + 1, length, chain)
+            })
+            .collect()
+    }
+
+    /// Which cards can be played directly after which: `matrix[i][j]` is whether card `j` can
+    /// follow card `i`. Cards are in the order of `all_cards`.
+    pub fn reachability_matrix(&self) -> Vec<Vec<bool>> {
+        let cards: Vec<&Card> = self.all_cards().collect();
+        cards.iter()
+            .map(|from| cards.iter().map(|to| can_follow(&from.output, to)).collect())
+            .collect()
+    }
+
+    /// The reachability matrix as a table, with a legend giving the card for each index. Rows are
+    /// the card played first, and columns the card played after it.
+    pub fn reachability_table(&self) -> String {
+        let cards: Vec<&Card> = self.all_cards().collect();
+        let matrix = self.reachability_matrix();
+        let width = cards.len().saturating_sub(1).to_string().len();
+        let mut table = String::new();
+        for (index, card) in cards.iter().enumerate() {
+            let anchor = if index == 0 && self.anchor.is_some() { " (anchor)" } else { "" };
+            table.push_str(&format!("{:>width$}: {}{}\n", index, card, anchor));
+        }
+        table.push_str(&format!("\n{:>width$} ", ""));
+        for index in 0..cards.len() {
+            table.push_str(&format!(" {:>width$}", index));
+        }
+        for (index, row) in matrix.iter().enumerate() {
+            table.push_str(&format!("\n{:>width$} ", index));
+            for reachable in row {
+                table.push_str(&format!(" {:>width$}", if *reachable { "X" } else { "." }));
+            }
+        }
+        table
+    }
+
+    /// The non-anchor cards that have `effect`.
+    pub fn cards_with_effect(&self, effect: &Effect) -> Vec<&Card> {
+        self.cards.iter()
+            .filter(|card| card.effect == *effect)
+            .collect()
+    }
+
+    /// For each connector type, how many more times it appears in an output than in an input,
+    /// counting the anchor. A negative balance means the deck asks for the type more often than
+    /// it supplies it.
+    pub fn connector_balance(&self) -> BTreeMap<ConnectType, i64> {
+        let mut balance: BTreeMap<ConnectType, i64> = ConnectType::iter().map(|connect_type| (connect_type, 0)).collect();
+        for card in self.all_cards() {
+            for connect_type in card.output.iter() {
+                *balance.entry(connect_type.clone()).or_default() += 1;
+            }
+            for connect_type in card.input.iter() {
+                *balance.entry(connect_type.clone()).or_default() -= 1;
+            }
+        }
+        balance
+    }
+
+    /// How many times each connector type is expected to appear in the inputs and outputs of a
+    /// hand of `hand_size` cards drawn at random from the non-anchor cards. Every card is equally
+    /// likely to be drawn, so each is in the hand with probability `hand_size / cards`; if the hand
+    /// is at least as big as the deck, the whole deck is drawn.
+    pub fn expected_hand_connectors(&self, hand_size: u8) -> BTreeMap<ConnectType, f64> {
+        let mut expected: BTreeMap<ConnectType, f64> = ConnectType::iter().map(|connect_type| (connect_type, 0.0)).collect();
+        if self.cards.is_empty() {
+            return expected;
+        }
+        let draw_chance = (hand_size as usize).min(self.cards.len()) as f64 / self.cards.len() as f64;
+        for card in &self.cards {
+            for connect_type in card.input.iter().chain(card.output.iter()) {
+                *expected.entry(connect_type.clone()).or_default() += draw_chance;
+            }
+        }
+        expected
+    }
+}
+
 impl_tryfrom_field!{Struct for Deck:
     |value| {
         field_get!(let cards: List = value.cards);
@@ -310,10 +1302,9 @@ impl_tryfrom_field!{Struct for Deck:
         }
 
         Ok(Self {
-            anchor: if let Some(anchor) = value.get("anchor") {
-                anchor.try_into()?
-            } else {
-                return Err("No `anchor` field for Deck.".into());
+            anchor: match value.get("anchor") {
+                Some(anchor) => Some(anchor.try_into()?),
+                None => None,
             },
             cards: deck,
         })
@@ -322,18 +1313,320 @@ impl_tryfrom_field!{Struct for Deck:
 
 impl Display for Deck {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "\t\tanchor: {}\n\t\t", self.anchor)?;
-        write_vec_sep(&self.cards, "\n\t\t", f)
+        let (i2, i3) = (indent(2), indent(3));
+        if self.anchor.is_none() && self.cards.is_empty() {
+            return write!(f, "{i2}(empty deck)");
+        }
+        write!(f, "{i2}{}\n{i2}", wrap_card_line(&format!("anchor: {}", describe_anchor(&self.anchor))))?;
+        if self.cards.is_empty() {
+            return write!(f, "(no non-anchor cards)");
+        }
+        let multiplicities = self.card_multiplicities();
+        let lines: Vec<String> = if settings().group_cards {
+            multiplicities.iter()
+                .map(|(card, count)| wrap_card_line(&format!("{}x {}", count, card)))
+                .collect()
+        } else {
+            self.cards.iter()
+                .map(|card| wrap_card_line(&card.to_string()))
+                .collect()
+        };
+        write_vec_sep(&lines, &format!("\n{i2}"), f)?;
+        write!(f, "\n{i2}unique cards: {}, total: {}", multiplicities.len(), self.cards.len())?;
+
+        // Only worth pointing out when there is more than the anchor to start from
+        let entry_points = self.entry_points();
+        if entry_points.len() > usize::from(self.anchor.is_some()) {
+            write!(f, "\n{i2}entry points:")?;
+            for card in entry_points {
+                write!(f, "\n{i3}{}", card)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Deck {
+    /// The lines of the deck's box in a reference sheet: the anchor, a divider, then the other
+    /// cards, grouped if `--group-cards` was given.
+    fn sheet_lines(&self) -> Vec<Option<String>> {
+        if self.anchor.is_none() && self.cards.is_empty() {
+            return vec![Some("(empty deck)".to_owned())];
+        }
+        let mut lines = vec![Some(format!("anchor: {}", describe_anchor(&self.anchor))), None];
+        if self.cards.is_empty() {
+            lines.push(Some("(no non-anchor cards)".to_owned()));
+        } else if settings().group_cards {
+            lines.extend(self.card_multiplicities().iter().map(|(card, count)| Some(format!("{}x {}", count, card))));
+        } else {
+            lines.extend(self.cards.iter().map(|card| Some(card.to_string())));
+        }
+        lines
+    }
+}
+
+/// Whether `card` can be played after a card whose output is `previous`, treating the
+/// `--wildcard` type as matching anything.
+fn can_follow(previous: &Connector, card: &Card) -> bool {
+    match &settings().wildcard {
+        Some(wildcard) => card.input.matches_with_wildcard(previous, wildcard),
+        None => card.input.matches(previous),
+    }
+}
+
+fn describe_anchor(anchor: &Option<Card>) -> String {
+    match anchor {
+        Some(anchor) => anchor.to_string(),
+        None => "no anchor".to_owned(),
     }
 }
 
-#[derive(Debug)]
+/// Break a card line that is too wide for the terminal at its ` | ` and ` + ` separators, so
+/// that no symbol or effect is split. Continuation lines get a hanging indent.
+/// Lines are left alone if the terminal width isn't known.
+fn wrap_card_line(line: &str) -> String {
+    let Some(width) = settings().terminal_width else { return line.to_owned(); };
+    // Decks are indented two levels deep
+    let deck_indent_width = settings().indent.width(2);
+    if deck_indent_width + visible_width(line) <= width {
+        return line.to_owned();
+    }
+
+    let mut segments = Vec::new();
+    let mut rest = line;
+    // Skip the first character so a segment starting with a separator isn't split again
+    let skip = |s: &str| s.chars().next().map_or(0, char::len_utf8);
+    while let Some(pos) = [" | ", " + "].iter().filter_map(|sep| rest[skip(rest)..].find(sep).map(|pos| pos + skip(rest))).min() {
+        segments.push(&rest[..pos]);
+        rest = &rest[pos..];
+    }
+    segments.push(rest);
+
+    let hanging = format!("\n{}    ", indent(2));
+    let mut wrapped = String::new();
+    let mut used = deck_indent_width;
+    for segment in segments {
+        if !wrapped.is_empty() && used + visible_width(segment) > width {
+            wrapped.push_str(&hanging);
+            used = deck_indent_width + 4;
+            let segment = segment.trim_start();
+            wrapped.push_str(segment);
+            used += visible_width(segment);
+        } else {
+            wrapped.push_str(segment);
+            used += visible_width(segment);
+        }
+    }
+    wrapped
+}
+
+/// The number of columns shown for `s`, ignoring ANSI colour codes. Emoji, such as 🐾, take up
+/// two columns in most terminals.
+fn visible_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut in_escape = false;
+    for c in s.chars() {
+        match c {
+            '\x1b' => in_escape = true,
+            'm' if in_escape => in_escape = false,
+            _ if in_escape => {},
+            '\u{1F000}'.. => width += 2,
+            _ => width += 1,
+        }
+    }
+    width
+}
+
+/// Draw `lines` in a box of box-drawing characters, with `title` in the top border.
+/// Lines that are `None` are drawn as dividers.
+fn draw_box(title: &str, lines: &[Option<String>]) -> String {
+    let title_width = visible_width(title);
+    let width = lines.iter().flatten()
+        .map(|line| visible_width(line))
+        .chain([title_width + 1])
+        .max()
+        .unwrap_or(0);
+    let mut text = format!("┌─ {} {}┐\n", title, "─".repeat(width - title_width - 1));
+    for line in lines {
+        match line {
+            Some(line) => text.push_str(&format!("│ {}{} │\n", line, " ".repeat(width - visible_width(line)))),
+            None => text.push_str(&format!("├{}┤\n", "─".repeat(width + 2))),
+        }
+    }
+    text.push_str(&format!("└{}┘\n", "─".repeat(width + 2)));
+    text
+}
+
+/// Cards `(added, removed)` in one deck relative to another.
+pub type DeckDiff<'a> = (Vec<&'a Card>, Vec<&'a Card>);
+
+#[allow(clippy::upper_case_acronyms)] // Named as the game and its players name them
+#[derive(Debug, Serialize, Deserialize)]
 pub struct NPC {
     pub id: String,
     pub hand_size: u8,
     pub prefers_doubles: bool,
     pub mad_threshold: u8,
-    pub decks: [Deck; 6],
+    /// `decks[0]` is the fallback deck, used if the game is somehow in an unexpected cycle.
+    /// The rest are the decks for each cycle in order, 5 in current versions of the game.
+    /// Use `deck_for_cycle` to look them up.
+    #[serde(serialize_with = "serialize_decks", deserialize_with = "deserialize_decks")]
+    pub decks: Vec<Deck>,
+    /// The Unity class id of the asset the NPC was loaded from, if known.
+    pub class_id: Option<u64>,
+    /// The asset file the NPC was loaded from, if it was loaded from a file.
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+/// Serialize an NPC's decks as a map with `fallback` and `cycle1`, `cycle2`, ... keys, so consumers
+/// don't need to know which index is the fallback.
+fn serialize_decks<S: Serializer>(decks: &[Deck], serializer: S) -> Result<S::Ok, S::Error> {
+    let Some(fallback) = decks.first() else {
+        return Err(S::Error::custom("an NPC needs at least a fallback deck"));
+    };
+    let mut map = serializer.serialize_map(Some(decks.len()))?;
+    map.serialize_entry("fallback", fallback)?;
+    for (cycle, deck) in decks.iter().enumerate().skip(1) {
+        map.serialize_entry(&format!("cycle{}", cycle), deck)?;
+    }
+    map.end()
+}
+
+/// Read back decks written by `serialize_decks`.
+fn deserialize_decks<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Deck>, D::Error> {
+    let mut decks: Vec<(usize, Deck)> = BTreeMap::<String, Deck>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, deck)| {
+            let index = match key.as_str() {
+                "fallback" => Some(0),
+                key => key.strip_prefix("cycle").and_then(|cycle| cycle.parse().ok()),
+            };
+            index.map(|index| (index, deck))
+                .ok_or_else(|| serde::de::Error::custom(format!("unexpected deck `{}`", key)))
+        })
+        .collect::<Result<_, _>>()?;
+    decks.sort_by_key(|(index, _)| *index);
+    // Each deck's place in the list is its cycle, so none can be left out
+    for (expected, (index, _)) in decks.iter().enumerate() {
+        if *index != expected {
+            return Err(serde::de::Error::custom(match expected {
+                0 => "an NPC needs at least a fallback deck".to_owned(),
+                cycle => format!("missing deck `cycle{}`", cycle),
+            }));
+        }
+    }
+    Ok(decks.into_iter().map(|(_, deck)| deck).collect())
+}
+
+/// An NPC attribute that can be compared in an `NPCPredicate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NPCField {
+    HandSize,
+    MadThreshold,
+    PrefersDoubles,
+}
+
+impl NPCField {
+    fn name(&self) -> &'static str {
+        match self {
+            NPCField::HandSize => "hand_size",
+            NPCField::MadThreshold => "mad_threshold",
+            NPCField::PrefersDoubles => "prefers_doubles",
+        }
+    }
+
+    /// The value of the field for `npc`, with `prefers_doubles` as 0 or 1.
+    fn value(&self, npc: &NPC) -> u8 {
+        match self {
+            NPCField::HandSize => npc.hand_size,
+            NPCField::MadThreshold => npc.mad_threshold,
+            NPCField::PrefersDoubles => npc.prefers_doubles as u8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    /// Operators ordered so that longer ones are tried first, e.g. `>=` before `>`.
+    const SYMBOLS: [(&'static str, CompareOp); 7] = [
+        ("==", CompareOp::Eq),
+        ("!=", CompareOp::Ne),
+        ("<=", CompareOp::Le),
+        (">=", CompareOp::Ge),
+        ("<", CompareOp::Lt),
+        (">", CompareOp::Gt),
+        ("=", CompareOp::Eq),
+    ];
+
+    fn holds(&self, left: u8, right: u8) -> bool {
+        match self {
+            CompareOp::Eq => left == right,
+            CompareOp::Ne => left != right,
+            CompareOp::Lt => left < right,
+            CompareOp::Le => left <= right,
+            CompareOp::Gt => left > right,
+            CompareOp::Ge => left >= right,
+        }
+    }
+}
+
+/// A simple `field op value` condition on an NPC, e.g. `hand_size >= 10` or `prefers_doubles == true`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NPCPredicate {
+    pub field: NPCField,
+    op: CompareOp,
+    value: u8,
+}
+
+impl NPCPredicate {
+    pub fn matches(&self, npc: &NPC) -> bool {
+        self.op.holds(self.field.value(npc), self.value)
+    }
+
+    /// The queried attribute of `npc`, formatted as `field = value`.
+    pub fn describe(&self, npc: &NPC) -> String {
+        match self.field {
+            NPCField::PrefersDoubles => format!("{} = {}", self.field.name(), npc.prefers_doubles),
+            field => format!("{} = {}", field.name(), field.value(npc)),
+        }
+    }
+}
+
+impl FromStr for NPCPredicate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((pos, symbol, op)) = CompareOp::SYMBOLS.iter()
+            .filter_map(|(symbol, op)| s.find(symbol).map(|pos| (pos, *symbol, *op)))
+            .min_by_key(|(pos, _, _)| *pos)
+        else {
+            return Err(format!("`{}` has no comparison; use one of == != < <= > >=", s));
+        };
+        let field = match s[..pos].trim() {
+            "hand_size" => NPCField::HandSize,
+            "mad_threshold" => NPCField::MadThreshold,
+            "prefers_doubles" => NPCField::PrefersDoubles,
+            other => { return Err(format!("Unknown NPC field `{}`; use hand_size, mad_threshold or prefers_doubles", other)); }
+        };
+        let value = s[pos + symbol.len()..].trim();
+        let value = match (field, value) {
+            (NPCField::PrefersDoubles, "true") => 1,
+            (NPCField::PrefersDoubles, "false") => 0,
+            (NPCField::PrefersDoubles, _) => { return Err(format!("prefers_doubles can only be compared to true or false, not `{}`", value)); }
+            (_, value) => value.parse().map_err(|_| format!("`{}` isn't a number from 0 to 255", value))?,
+        };
+        Ok(Self { field, op, value })
+    }
 }
 
 impl PartialEq for NPC {
@@ -360,26 +1653,22 @@ impl_tryfrom_field!{Struct for NPC:
         field_get!(let hand_size: Uint = field.handSize);
         field_get!(let doubles: Uint = field.prefersDoubles);
         field_get!(let mad: Uint = field.mad);
-        field_get!(let deck0: Struct = field.deck0);
-        field_get!(let deck1: Struct = field.deck1);
-        field_get!(let deck2: Struct = field.deck2);
-        field_get!(let deck3: Struct = field.deck3);
-        field_get!(let deck4: Struct = field.deck4);
-        field_get!(let deck5: Struct = field.deck5);
+        let mut decks = Vec::new();
+        while let Some(deck) = field.get(&format!("deck{}", decks.len())) {
+            decks.push(deck.try_into()?);
+        }
+        if decks.is_empty() {
+            return Err("No `deck0` field for NPC.".into());
+        }
 
         Ok(Self {
             id: id.to_owned(),
-            hand_size: *hand_size as u8,
-            prefers_doubles: *doubles != 0,
-            mad_threshold: *mad as u8,
-            decks: [
-                deck0.try_into()?,
-                deck1.try_into()?,
-                deck2.try_into()?,
-                deck3.try_into()?,
-                deck4.try_into()?,
-                deck5.try_into()?,
-            ]
+            hand_size: hand_size as u8,
+            prefers_doubles: doubles != 0,
+            mad_threshold: mad as u8,
+            decks,
+            class_id: None,
+            source: None,
         })
     }
 }
@@ -389,38 +1678,462 @@ impl NPC {
         map.contains_key("deck0")
     }
 
-    pub fn load_asset(path: PathBuf) -> Result<Option<Self>, Box<dyn Error>> {
-        let yaml: Field = serde_yaml::from_reader(File::open(path)?)?;
-        let Field::Struct(data_map) = yaml else { return Err("Root isn't a map".into()); };
-        let ref_data_map = &data_map;
-        field_get!(let monobehaviour: Struct = ref_data_map.MonoBehaviour);
-        if NPC::is_npc(monobehaviour) {
-            let npc = monobehaviour.try_into()?;
-            Ok(Some(npc))
-        } else {
-            Ok(None)
+    pub fn load_asset(path: &Path, resolve: &GuidResolver) -> Result<Option<Self>, Box<dyn Error + Send + Sync>> {
+        let mut npc = parse_npc(&fs::read_to_string(path)?, resolve)?;
+        if let Some(npc) = &mut npc {
+            npc.source = Some(path.to_owned());
         }
+        Ok(npc)
     }
 
     pub fn print_details(&self) {
+        let i1 = indent(1);
         println!("NPC - {}:", self.id);
-        println!("\tHand Size: {}", self.hand_size);
-        println!("\tPrefers Doubles: {}", self.prefers_doubles);
-        println!("\tDiscordances to become mad: {}", self.mad_threshold);
+        println!("{i1}Hand Size: {}", self.hand_size);
+        println!("{i1}Prefers Doubles: {}", self.prefers_doubles);
+        println!("{i1}Discordances to become mad: {}", self.mad_threshold);
+
+        let i2 = indent(2);
+        let anchor_profile = self.anchor_effect_profile();
+        println!("{i1}Effects across all decks, anchors included:");
+        for (effect, count) in self.effect_profile() {
+            let name = if effect == Effect::None { "No effect" } else { effect.label() };
+            match anchor_profile.get(&effect) {
+                Some(anchors) => println!("{i2}{}: {} ({} on anchors)", name, count, anchors),
+                None => println!("{i2}{}: {}", name, count),
+            }
+        }
+    }
+
+    /// How many cards have each effect across all of the NPC's decks, including the fallback deck
+    /// and the anchor cards.
+    pub fn effect_profile(&self) -> BTreeMap<Effect, usize> {
+        let mut profile = BTreeMap::new();
+        for card in self.decks.iter().flat_map(|deck| deck.all_cards()) {
+            *profile.entry(card.effect.clone()).or_insert(0) += 1;
+        }
+        profile
+    }
+
+    /// The part of `effect_profile` that comes from anchor cards.
+    fn anchor_effect_profile(&self) -> BTreeMap<Effect, usize> {
+        let mut profile = BTreeMap::new();
+        for anchor in self.decks.iter().filter_map(|deck| deck.anchor.as_ref()) {
+            *profile.entry(anchor.effect.clone()).or_insert(0) += 1;
+        }
+        profile
+    }
+
+    /// The number of cycles the NPC has a deck for, not counting the fallback deck.
+    pub fn cycle_count(&self) -> usize {
+        self.decks.len() - 1
+    }
+
+    /// Get the deck used in `cycle`, where `None` or cycle 0 means the fallback deck.
+    pub fn deck_for_cycle(&self, cycle: Option<usize>) -> Result<&Deck, Box<dyn Error>> {
+        match cycle {
+            None | Some(0) => Ok(&self.decks[0]),
+            Some(cycle) if (1..=self.cycle_count()).contains(&cycle) => Ok(&self.decks[cycle]),
+            Some(cycle) => Err(format!("{} has no deck for cycle {}; cycles run from 1 to {}.", self.id, cycle, self.cycle_count()).into()),
+        }
+    }
+
+    pub fn print_deck(&self, cycle: usize) -> Result<(), Box<dyn Error>> {
+        print!("{}", self.deck_text(cycle)?);
+        Ok(())
+    }
+
+    fn deck_text(&self, cycle: usize) -> Result<String, Box<dyn Error>> {
+        let deck = self.deck_for_cycle(Some(cycle))?;
+        Ok(format!("{}Deck for cycle {}:\n{}\n", indent(1), cycle, deck))
+    }
+
+    pub fn print_fallback_deck(&self) -> Result<(), Box<dyn Error>> {
+        print!("{}", self.fallback_deck_text()?);
+        Ok(())
+    }
+
+    fn fallback_deck_text(&self) -> Result<String, Box<dyn Error>> {
+        let deck = self.deck_for_cycle(None)?;
+        Ok(format!("{}Fallback deck (unexpected cycle value):\n{}\n", indent(1), deck))
+    }
+
+    /// Compare the non-anchor cards of the deck for `cycle` against the fallback deck.
+    /// Returns the cards `(added, removed)` relative to the fallback.
+    pub fn deck_diff_from_fallback(&self, cycle: usize) -> Result<DeckDiff<'_>, Box<dyn Error>> {
+        let fallback = self.deck_for_cycle(None)?;
+        let deck = self.deck_for_cycle(Some(cycle))?;
+        Ok(deck.diff(fallback))
+    }
+
+    /// A line for each difference from `older`, such as `hand_size: 5 -> 6` or
+    /// `cycle 2 deck: + ○ | △`.
+    pub fn describe_changes(&self, older: &NPC) -> Vec<String> {
+        let mut changes = Vec::new();
+        for (name, old, new) in [
+            ("hand_size", older.hand_size.to_string(), self.hand_size.to_string()),
+            ("prefers_doubles", older.prefers_doubles.to_string(), self.prefers_doubles.to_string()),
+            ("mad_threshold", older.mad_threshold.to_string(), self.mad_threshold.to_string()),
+        ] {
+            if old != new {
+                changes.push(format!("{}: {} -> {}", name, old, new));
+            }
+        }
+        for index in 0..self.decks.len().max(older.decks.len()) {
+            let name = if index == 0 { "fallback deck".to_owned() } else { format!("cycle {} deck", index) };
+            match (older.decks.get(index), self.decks.get(index)) {
+                (Some(old), Some(new)) => changes.extend(new.describe_changes(old).into_iter().map(|change| format!("{}: {}", name, change))),
+                (None, Some(_)) => changes.push(format!("{}: added", name)),
+                (Some(_), None) => changes.push(format!("{}: removed", name)),
+                (None, None) => {},
+            }
+        }
+        changes
+    }
+
+    pub fn print_deck_diff(&self, cycle: usize) -> Result<(), Box<dyn Error>> {
+        let fallback = self.deck_for_cycle(None)?;
+        let deck = self.deck_for_cycle(Some(cycle))?;
+        let (i1, i2) = (indent(1), indent(2));
+        println!("{i1}Deck for cycle {} compared to the fallback deck:", cycle);
+        let changes = deck.describe_diff(fallback, &self.deck_diff_from_fallback(cycle)?);
+        if changes.is_empty() {
+            println!("{i2}Same cards as the fallback deck");
+        }
+        changes.iter().for_each(|change| println!("{i2}{}", change));
+        Ok(())
+    }
+
+    pub fn print_all_decks(&self) -> Result<(), Box<dyn Error>> {
+        print!("{}", self.all_decks_text()?);
+        Ok(())
+    }
+
+    /// Every deck drawn in a box, one per cycle followed by the fallback deck, for pasting into
+    /// notes or printing.
+    pub fn reference_sheet(&self) -> String {
+        let mut sheet = format!("{}\n", self.id);
+        for cycle in 1..=self.cycle_count() {
+            sheet.push_str(&draw_box(&format!("Cycle {}", cycle), &self.decks[cycle].sheet_lines()));
+        }
+        sheet.push_str(&draw_box("Fallback", &self.decks[0].sheet_lines()));
+        sheet
+    }
+
+    /// Every deck for each cycle, followed by the fallback deck.
+    pub fn all_decks_text(&self) -> Result<String, Box<dyn Error>> {
+        let mut text = String::new();
+        for cycle in 1..=self.cycle_count() {
+            text.push_str(&self.deck_text(cycle)?);
+        }
+        text.push_str(&self.fallback_deck_text()?);
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn every_connector_style_parses_back() {
+        let all: Vec<ConnectType> = ConnectType::iter().collect();
+        for style in ConnectorStyle::value_variants() {
+            let shown: Vec<&str> = all.iter().map(|connect_type| connect_type.symbol(*style)).collect();
+            assert_eq!(ConnectType::parse_symbols(&shown.join(" ")).unwrap(), all, "{:?} spaced", style);
+            assert_eq!(ConnectType::parse_symbols(&shown.concat()).unwrap(), all, "{:?} run together", style);
+        }
+    }
+
+    #[test]
+    fn connector_symbols_mix_styles_and_case() {
+        use ConnectType::*;
+        assert_eq!(ConnectType::parse_symbols("(o)t PAW p").unwrap(), vec![Circle, Triangle, Dog, Dog]);
+        assert_eq!("/_\\ []".parse::<Connector>().unwrap().sorted_types(), vec![Triangle, Square]);
+        assert_eq!("paw".parse::<ConnectType>(), Ok(Dog));
+    }
+
+    #[test]
+    fn unknown_connector_symbol_is_named() {
+        assert_eq!("○x".parse::<Connector>(), Err("Unknown connector symbol `x`".to_owned()));
+    }
+
+    #[test]
+    fn connector_types_are_in_canonical_order() {
+        use ConnectType::*;
+        let connector: Connector = "@🐾◊□△○".parse().unwrap();
+        assert_eq!(connector.sorted_types(), vec![Circle, Triangle, Square, Diamond, Dog, Spiral]);
+        assert_eq!(connector.to_string(), "○△□◊🐾@");
+        assert_eq!(serde_json::to_value(&connector).unwrap(), serde_json::json!(["Circle", "Triangle", "Square", "Diamond", "Dog", "Spiral"]));
+    }
+
+    #[test]
+    fn wildcard_on_either_side_matches() {
+        let connector = |s: &str| s.parse::<Connector>().unwrap();
+        let (circle, square) = (connector("○"), connector("□"));
+        assert!(!circle.matches_with_wildcard(&square, &ConnectType::Spiral));
+        assert!(connector("○ @").matches_with_wildcard(&square, &ConnectType::Spiral));
+        assert!(circle.matches_with_wildcard(&connector("□ @"), &ConnectType::Spiral));
+        assert!(connector("△").matches_with_wildcard(&connector("○ △"), &ConnectType::Spiral));
+    }
+
+    #[test]
+    fn wildcard_only_applies_to_the_chosen_type() {
+        let connector = |s: &str| s.parse::<Connector>().unwrap();
+        assert!(!connector("○ @").matches_with_wildcard(&connector("□"), &ConnectType::Dog));
+        assert!(connector("○ 🐾").matches_with_wildcard(&connector("□"), &ConnectType::Dog));
+        assert!(!connector("").matches_with_wildcard(&connector("□"), &ConnectType::Dog));
+    }
+
+    #[test]
+    fn connector_bits_round_trip() {
+        for bits in 0..=0x3f {
+            let connector = Connector::try_from(&Field::Uint(bits)).unwrap();
+            assert_eq!(connector.to_bits(), bits);
+        }
+    }
+
+    #[test]
+    fn deck_round_trips_through_asset_yaml() {
+        let npc = fixtures::npc();
+        let decks = fixtures::event("ev_custom").deck.into_iter().chain(npc.decks);
+        for deck in decks {
+            let field: Field = serde_yaml::from_str(&deck.to_asset_yaml()).unwrap();
+            assert_eq!(Deck::try_from(&field).unwrap(), deck);
+        }
+    }
+
+    #[test]
+    fn empty_deck_round_trips_through_asset_yaml() {
+        let deck = Deck { anchor: None, cards: Vec::new() };
+        let field: Field = serde_yaml::from_str(&deck.to_asset_yaml()).unwrap();
+        assert_eq!(Deck::try_from(&field).unwrap(), deck);
+    }
+
+    #[test]
+    fn events_round_trip_through_json() {
+        let events = fixtures::events_in(fixtures::EVENT_DATA);
+        let json = serde_json::to_string(&events).unwrap();
+        assert_eq!(events_from_json(&json).unwrap(), events);
+    }
+
+    #[test]
+    fn event_changes_are_described() {
+        let older = fixtures::event("ev_custom");
+        let mut newer = fixtures::event("ev_custom");
+        assert!(newer.describe_changes(&older).is_empty());
+        newer.strike_count = 3;
+        newer.deck.as_mut().unwrap().cards.pop();
+        assert_eq!(newer.describe_changes(&older), vec!["strike_count: 1 -> 3".to_owned(), "deck: - □ | 🐾 + Prepare".to_owned()]);
+    }
+
+    #[test]
+    fn npc_decks_serialize_with_named_keys() {
+        let json = serde_json::to_value(fixtures::npc()).unwrap();
+        let keys: BTreeSet<&str> = json["decks"].as_object().unwrap().keys().map(String::as_str).collect();
+        assert_eq!(keys, BTreeSet::from(["fallback", "cycle1", "cycle2", "cycle3", "cycle4", "cycle5"]));
+    }
+
+    #[test]
+    fn decks_without_a_fallback_fail_to_serialize() {
+        assert!(serialize_decks(&[], serde_json::value::Serializer).is_err());
+    }
+
+    #[test]
+    fn decks_need_a_fallback_and_every_cycle() {
+        let decks = |keys: &[&str]| {
+            let deck = serde_json::to_value(Deck { anchor: None, cards: Vec::new() }).unwrap();
+            let json = keys.iter().map(|key| (key.to_string(), deck.clone())).collect();
+            deserialize_decks(serde_json::Value::Object(json)).map_err(|err| err.to_string())
+        };
+        assert_eq!(decks(&["fallback", "cycle1", "cycle2"]).map(|decks| decks.len()), Ok(3));
+        assert_eq!(decks(&["cycle1"]).map(|decks| decks.len()), Err("an NPC needs at least a fallback deck".to_owned()));
+        assert_eq!(decks(&["fallback", "cycle2"]).map(|decks| decks.len()), Err("missing deck `cycle1`".to_owned()));
+    }
+
+    #[test]
+    fn event_display_matches_golden() {
+        fixtures::assert_golden("raw_event_override_deck", &fixtures::event("ev_custom").to_string());
+        fixtures::assert_golden("raw_event_npc_deck", &fixtures::event("ev_intro").to_string());
+    }
+
+    #[test]
+    fn npc_decks_match_golden() {
+        let npc = fixtures::npc();
+        fixtures::assert_golden("npc_all_decks", &npc.all_decks_text().unwrap());
+        fixtures::assert_golden("npc_reference_sheet", &npc.reference_sheet());
+    }
+
+    #[test]
+    fn connector_counts_add_up() {
+        let deck = fixtures::event("ev_custom").deck.unwrap();
+        let counts: Vec<usize> = deck.anchor.iter().chain(&deck.cards).map(Card::total_connector_count).collect();
+        // The anchor has only an output; the last card has a type on each side
+        assert_eq!(counts, vec![1, 2, 2, 3, 2]);
+        assert_eq!(deck.total_weight(), 10);
+        assert_eq!(Deck { anchor: None, cards: Vec::new() }.total_weight(), 0);
+    }
+
+    #[test]
+    fn all_cards_yields_anchor_first_and_once() {
+        let deck = fixtures::event("ev_custom").deck.unwrap();
+        let all: Vec<&Card> = deck.all_cards().collect();
+        assert_eq!(all.len(), deck.cards.len() + 1);
+        assert_eq!(Some(all[0]), deck.anchor.as_ref());
+        assert_eq!(all.iter().filter(|card| Some(**card) == deck.anchor.as_ref()).count(), 1);
+        assert!(all[1..].iter().copied().eq(deck.cards.iter()));
+
+        let no_anchor = Deck { anchor: None, cards: deck.cards.clone() };
+        assert!(no_anchor.all_cards().eq(deck.cards.iter()));
+    }
+
+    #[test]
+    fn deck_without_cards_says_so() {
+        let i2 = indent(2);
+        let anchor_only = Deck { anchor: fixtures::event("ev_custom").deck.unwrap().anchor, cards: Vec::new() };
+        assert_eq!(anchor_only.to_string(), format!("{i2}anchor:  | ○\n{i2}(no non-anchor cards)"));
+        let empty = Deck { anchor: None, cards: Vec::new() };
+        assert_eq!(empty.to_string(), format!("{i2}(empty deck)"));
+        assert_eq!(empty.sheet_lines(), vec![Some("(empty deck)".to_owned())]);
+    }
+
+    #[test]
+    fn crlf_parses_like_lf() {
+        let crlf = fixtures::EVENT_DATA.replace('\n', "\r\n");
+        assert_eq!(fixtures::events_in(&crlf), fixtures::events_in(fixtures::EVENT_DATA));
+        assert_eq!(parse_sequence("ev", "03000000\r\n04000000\r05000000").unwrap(), vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn listed_sequence_matches_packed() {
+        let listed = fixtures::EVENT_DATA.replace("sequence: 030000000400000005000000", "sequence: [3, 4, 5]");
+        let event = fixtures::events_in(&listed).into_iter().find(|event| event.id == "ev_intro").unwrap();
+        assert_eq!(event.sequence_lengths, fixtures::event("ev_intro").sequence_lengths);
+        assert_eq!(event.raw_sequence, "[3, 4, 5]");
+
+        let wrong = fixtures::EVENT_DATA.replace("sequence: 030000000400000005000000", "sequence: [3, four, 5]");
+        assert!(matches!(parse_asset(&wrong), Err(YamlError::WrongType { expected: "List of Uint", .. })));
+    }
+
+    #[test]
+    fn deck_changes_count_duplicate_cards() {
+        let older = fixtures::event("ev_custom").deck.unwrap();
+        let mut newer = fixtures::event("ev_custom").deck.unwrap();
+        newer.cards.push(newer.cards[0].clone());
+        assert_eq!(newer.describe_changes(&older), vec![format!("+ {}", older.cards[0])]);
+        assert_eq!(older.describe_changes(&newer), vec![format!("- {}", older.cards[0])]);
+    }
+
+    #[test]
+    fn npc_changes_name_the_cycle() {
+        let older = fixtures::npc();
+        let mut newer = fixtures::npc();
+        newer.hand_size = 6;
+        newer.decks[2].cards.pop();
+        assert_eq!(newer.describe_changes(&older), vec![
+            "hand_size: 5 -> 6".to_owned(),
+            format!("cycle 2 deck: - {}", older.decks[2].cards[2]),
+        ]);
+    }
+
+    #[test]
+    fn packed_lengths_of_ten_or_more() {
+        assert_eq!(parse_sequence("ev", "0a0000000c000000ff000000").unwrap(), vec![10, 12, 255]);
+        // Lengths are little-endian, so `10000000` is 16 and `00010000` would be 256
+        assert_eq!(parse_sequence("ev", "1000000000010000").unwrap(), vec![16]);
+    }
+
+    #[test]
+    fn sequence_count_must_match_packed_lengths() {
+        let short = fixtures::EVENT_DATA.replacen("sequenceCount: 3", "sequenceCount: 4", 1);
+        let err = parse_asset(&short).unwrap_err();
+        assert_eq!(err.to_string(), "ev_intro: Failed to parse `sequence` field.");
+    }
+
+    #[test]
+    fn path_between_finds_the_shortest_chain() {
+        let connector = |s: &str| s.parse::<Connector>().unwrap();
+        let circle_to_square = Card::new(connector("○"), connector("□"), Effect::None);
+        let square_to_triangle = Card::new(connector("□"), connector("△"), Effect::None);
+        let circle_to_triangle = Card::new(connector("○"), connector("△"), Effect::None);
+        let deck = Deck { anchor: None, cards: vec![circle_to_square.clone(), square_to_triangle.clone(), circle_to_triangle.clone()] };
+        assert_eq!(deck.path_between(&connector("○"), &connector("△")), Some(vec![&circle_to_triangle]));
+        assert_eq!(deck.path_between(&connector("○"), &connector("□")), Some(vec![&circle_to_square]));
+        assert_eq!(deck.path_between(&connector("□"), &connector("□")), Some(Vec::new()));
+        // Nothing takes a triangle, and no card gives a circle and a square together
+        assert_eq!(deck.path_between(&connector("△"), &connector("○")), None);
+        assert_eq!(deck.path_between(&connector("○"), &connector("○□")), None);
+    }
+
+    #[test]
+    fn unknown_event_keys_are_ignored() {
+        let extra = fixtures::EVENT_DATA
+            .replace("  data:\n", "  m_Script: {fileID: 11500000, guid: cccc3333, type: 3}\n  m_EditorClassIdentifier: \n  data:\n")
+            .replace("    strikeCount:", "    notes: [first, {second: 2}]\n    timeLimit: 1.5e-05\n    strikeCount:");
+        assert_eq!(fixtures::events_in(&extra), fixtures::events_in(fixtures::EVENT_DATA));
+    }
+
+    #[test]
+    fn only_deck_keys_are_resolved() {
+        let mut contents = fixtures::NPC_ASSET.replace("  mad: 3\n", concat!(
+            "  mad: 3\n",
+            "  m_Script: {fileID: 11500000, guid: cccc3333, type: 3}\n",
+            "  deckNotes: {fileID: 11400000, guid: dddd4444, type: 2}\n",
+            "  deck: {fileID: 11400000, guid: eeee5555, type: 2}\n",
+        ));
+        for cycle in 6..10 {
+            contents.push_str(&format!("  deck{}:\n    anchor: {{input: 0, output: 1, effect: 0}}\n    cards: []\n", cycle));
+        }
+        contents.push_str("  deck10: {fileID: 11400000, guid: bbbb2222, type: 2}\n");
+
+        let resolve = |guid: &str| match guid {
+            "bbbb2222" => Ok(serde_yaml::from_str("{anchor: {input: 0, output: 2, effect: 0}, cards: []}")?),
+            _ => Err(format!("`{}` isn't a deck", guid).into()),
+        };
+        let npc = parse_npc(&contents, &resolve).unwrap().unwrap();
+        assert_eq!(npc.cycle_count(), 10);
+        assert_eq!(npc.decks[10].to_asset_yaml(), "anchor: {input: 0, output: 2, effect: 0}\ncards: []");
+        assert_eq!(npc.decks[..6], fixtures::npc().decks[..]);
+    }
+
+    #[test]
+    fn long_chains_are_found_quickly() {
+        let connector = |s: &str| s.parse::<Connector>().unwrap();
+        let circle = Card::new(connector("○"), connector("○"), Effect::None);
+        let square = Card::new(connector("□"), connector("□"), Effect::None);
+        // Without remembering dead ends, finding that there's no chain of 41 would try every
+        // order of the 40 cards that can follow each other
+        let mut cards = vec![circle.clone(); 40];
+        cards.push(square.clone());
+        let deck = Deck { anchor: Some(Card::new(Connector::default(), connector("○"), Effect::None)), cards };
+
+        let start = std::time::Instant::now();
+        assert_eq!(deck.chain_of_length(40), Some(vec![&circle; 40]));
+        assert_eq!(deck.chain_of_length(41), None);
+        assert!(start.elapsed() < std::time::Duration::from_secs(1), "took {:?}", start.elapsed());
     }
 
-    pub fn print_deck(&self, cycle: usize) {
-        println!("\tDeck for cycle {}:", cycle);
-        println!("{}", self.decks[cycle]);
+    #[test]
+    fn chain_uses_each_card_once() {
+        let deck = fixtures::event("ev_custom").deck.unwrap();
+        let chain = deck.chain_of_length(3).unwrap();
+        assert_eq!(chain, vec![&deck.cards[0], &deck.cards[2], &deck.cards[1]]);
+        assert_eq!(deck.chain_of_length(4), None);
     }
 
-    pub fn print_fallback_deck(&self) {
-        println!("\tFallback deck (unexpected cycle value):");
-        println!("{}", self.decks[0]);
+    #[test]
+    fn difficulty_follows_formula() {
+        // 3 * 3 concords + (3 + 4 + 5) cards - 2 * 2 discords
+        assert_eq!(fixtures::event("ev_intro").difficulty(), 17);
+        // 3 * 2 + (2 + 3) - 2 * 1
+        assert_eq!(fixtures::event("ev_custom").difficulty(), 9);
     }
 
-    pub fn print_all_decks(&self) {
-        (1..=5).for_each(|i| NPC::print_deck(self, i));
-        self.print_fallback_deck();
+    #[test]
+    fn difficulty_is_floored_at_zero() {
+        let mut event = fixtures::event("ev_custom");
+        event.strike_count = 20;
+        assert_eq!(event.difficulty(), 0);
     }
 }
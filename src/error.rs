@@ -0,0 +1,73 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    io,
+};
+
+use crate::yaml::YamlError;
+
+/// The assets were read and parsed, but don't make sense together.
+#[derive(Debug)]
+pub struct ValidationError(String);
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for ValidationError {}
+
+impl From<String> for ValidationError {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+/// Why the program failed, which decides its exit code.
+#[derive(Debug)]
+pub enum Failure {
+    /// A file couldn't be found or read. Exit code 5, as 2 is what clap uses for bad arguments.
+    Io(Box<dyn Error>),
+    /// An asset file couldn't be parsed. Exit code 3.
+    Parse(Box<dyn Error>),
+    /// The assets parsed, but don't make sense together. Exit code 4.
+    Validation(Box<dyn Error>),
+    /// Anything else. Exit code 1.
+    Other(Box<dyn Error>),
+}
+
+impl Failure {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Failure::Other(_) => 1,
+            Failure::Parse(_) => 3,
+            Failure::Validation(_) => 4,
+            Failure::Io(_) => 5,
+        }
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::Io(err) | Failure::Parse(err) | Failure::Validation(err) | Failure::Other(err) => {
+                write!(f, "{}", err)
+            }
+        }
+    }
+}
+
+impl From<Box<dyn Error>> for Failure {
+    fn from(value: Box<dyn Error>) -> Self {
+        if value.is::<io::Error>() || value.is::<walkdir::Error>() {
+            Failure::Io(value)
+        } else if value.is::<YamlError>() || value.is::<serde_yaml::Error>() || value.is::<serde_json::Error>() || value.is::<bincode::Error>() {
+            Failure::Parse(value)
+        } else if value.is::<ValidationError>() {
+            Failure::Validation(value)
+        } else {
+            Failure::Other(value)
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Small but realistic assets for tests, written the way the game stores them.
+
+use std::{
+    fs,
+    path::PathBuf,
+};
+
+use crate::{
+    data::{ parse_asset, parse_npc, RawEvent, NPC },
+    yaml::{ Field, UnityAsset },
+};
+
+/// The guid of `NPC_ASSET`, as given in `NPC_META`.
+pub const NPC_GUID: &str = "aaaa1111";
+
+/// An `event_data.asset` with an event that uses its NPC's deck and one that overrides it.
+pub const EVENT_DATA: &str = "\
+%YAML 1.1
+%TAG !u! tag:unity3d.com,2011:
+--- !u!114 &11400000
+MonoBehaviour:
+  m_ObjectHideFlags: 0
+  m_Name: event_data
+  data:
+  - id: ev_intro
+    sequence: 030000000400000005000000
+    sequenceCount: 3
+    strikeCount: 2
+    overrideDeck: 0
+    npc: {fileID: 11400000, guid: aaaa1111, type: 2}
+    deck:
+      anchor: {input: 0, output: 0, effect: 0}
+      cards: []
+  - id: ev_custom
+    sequence: 0200000003000000
+    sequenceCount: 2
+    strikeCount: 1
+    overrideDeck: 1
+    npc: {fileID: 11400000, guid: aaaa1111, type: 2}
+    deck:
+      anchor: {input: 0, output: 1, effect: 0}
+      cards:
+      - {input: 1, output: 2, effect: 1}
+      - {input: 1, output: 2, effect: 1}
+      - {input: 2, output: 5, effect: 0}
+      - {input: 4, output: 32, effect: 8}
+";
+
+/// An NPC with a fallback deck and a deck for each of the five cycles.
+pub const NPC_ASSET: &str = "\
+%YAML 1.1
+%TAG !u! tag:unity3d.com,2011:
+--- !u!114 &11400000
+MonoBehaviour:
+  m_ObjectHideFlags: 0
+  m_Name: alice
+  id: alice
+  handSize: 5
+  prefersDoubles: 0
+  mad: 3
+  deck0:
+    anchor: {input: 0, output: 1, effect: 0}
+    cards:
+    - {input: 1, output: 2, effect: 0}
+    - {input: 2, output: 1, effect: 3}
+    - {input: 4, output: 8, effect: 6}
+  deck1:
+    anchor: {input: 0, output: 1, effect: 0}
+    cards:
+    - {input: 1, output: 2, effect: 0}
+    - {input: 2, output: 2, effect: 3}
+    - {input: 4, output: 8, effect: 6}
+  deck2:
+    anchor: {input: 0, output: 1, effect: 0}
+    cards:
+    - {input: 1, output: 2, effect: 0}
+    - {input: 2, output: 3, effect: 3}
+    - {input: 4, output: 8, effect: 6}
+  deck3:
+    anchor: {input: 0, output: 1, effect: 0}
+    cards:
+    - {input: 1, output: 2, effect: 0}
+    - {input: 2, output: 4, effect: 3}
+    - {input: 4, output: 8, effect: 6}
+  deck4:
+    anchor: {input: 0, output: 1, effect: 0}
+    cards:
+    - {input: 1, output: 2, effect: 0}
+    - {input: 2, output: 5, effect: 3}
+    - {input: 4, output: 8, effect: 6}
+  deck5:
+    anchor: {input: 0, output: 1, effect: 0}
+    cards:
+    - {input: 1, output: 2, effect: 0}
+    - {input: 2, output: 6, effect: 3}
+    - {input: 4, output: 8, effect: 6}
+";
+
+/// The `.meta` file that goes with `NPC_ASSET`.
+pub const NPC_META: &str = "\
+fileFormatVersion: 2
+guid: aaaa1111
+NativeFormatImporter:
+  mainObjectFileID: 11400000
+";
+
+/// The whole of an asset as a `Field`, with the `MonoBehaviour` at the top.
+pub fn field(contents: &str) -> Field {
+    UnityAsset::parse(contents).expect("fixture should parse").root
+}
+
+/// The events in `contents`, which is laid out like `EVENT_DATA`.
+pub fn events_in(contents: &str) -> Vec<RawEvent> {
+    parse_asset(contents).expect("fixture events should parse")
+}
+
+/// The event in `EVENT_DATA` with the given id.
+pub fn event(id: &str) -> RawEvent {
+    events_in(EVENT_DATA).into_iter()
+        .find(|event| event.id == id)
+        .expect("no such fixture event")
+}
+
+/// The NPC in `contents`, which is laid out like `NPC_ASSET` and has every deck in the same file.
+pub fn npc_in(contents: &str) -> NPC {
+    parse_npc(contents, &|guid| Err(format!("fixtures don't have an asset with guid `{}`", guid).into()))
+        .expect("fixture NPC should parse")
+        .expect("fixture should be an NPC")
+}
+
+/// The NPC in `NPC_ASSET`.
+pub fn npc() -> NPC {
+    npc_in(NPC_ASSET)
+}
+
+/// Write `files`, given as `(name, contents)`, to an empty folder of their own for tests that
+/// load a whole folder. `name` has to be different for each test, as tests run at the same time.
+pub fn folder(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("sots-event-inspect-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).expect("couldn't create fixture folder");
+    for (file, contents) in files {
+        fs::write(path.join(file), contents).expect("couldn't write fixture file");
+    }
+    path
+}
+
+/// A folder holding `EVENT_DATA` and `NPC_ASSET`, named as the game names them.
+pub fn asset_folder(name: &str) -> PathBuf {
+    folder(name, &[
+        ("event_data.asset", EVENT_DATA),
+        ("alice.asset", NPC_ASSET),
+        ("alice.asset.meta", NPC_META),
+    ])
+}
+
+/// Check `actual` against the expected output checked in as `tests/golden/<name>.txt`, which is
+/// written instead when `SOTS_UPDATE_GOLDEN` is set. The expected output uses the default settings.
+pub fn assert_golden(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(format!("{}.txt", name));
+    if std::env::var_os("SOTS_UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().expect("golden files are in a folder")).expect("couldn't create golden folder");
+        fs::write(&path, actual).expect("couldn't write golden file");
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("couldn't read {}: {}; run with SOTS_UPDATE_GOLDEN=1 to create it", path.display(), err));
+    assert_eq!(actual, expected, "output differs from {}", path.display());
+}
+
+mod tests {
+    use std::ffi::OsStr;
+
+    use clap::Parser;
+
+    use super::*;
+    use crate::{ interface::App, Args };
+
+    #[test]
+    fn events_cover_both_kinds_of_deck() {
+        assert!(event("ev_intro").deck.is_none());
+        assert!(event("ev_custom").deck.is_some());
+        assert_eq!(event("ev_intro").npc_guid, NPC_GUID);
+    }
+
+    #[test]
+    fn npc_has_all_six_decks() {
+        let npc = npc();
+        assert_eq!(npc.id, "alice");
+        assert_eq!(npc.cycle_count(), 5);
+        assert!(field(NPC_ASSET).get("MonoBehaviour").and_then(|npc| npc.get("deck5")).is_some());
+    }
+
+    #[test]
+    fn folder_loads() {
+        let path = asset_folder("folder_loads");
+        let args = Args::try_parse_from([OsStr::new("sots-event-inspect"), path.as_os_str()]).unwrap();
+        assert!(App::new(args, &path).is_ok());
+    }
+}
@@ -1,29 +1,57 @@
-use inquire::{Select, InquireError, Confirm};
+use inquire::{
+    autocompletion::Replacement,
+    validator::Validation,
+    Autocomplete, Confirm, CustomType, CustomUserError, InquireError, Select, Text,
+};
 use strum::{ IntoEnumIterator, EnumIter };
+use clap::ValueEnum;
 use walkdir::WalkDir;
 use bimap::BiBTreeMap;
+use rayon::prelude::*;
+use serde::{ Deserialize, Serialize };
 
 use crate::{
-    data::{ RawEvent, NPC, write_vec_sep },
-    yaml::{ constrain_field_get_body, Field, YamlError },
-    field_get, field_get_body, field_value_type, Args, 
+    data::{ Card, CardQuery, ConnectType, Connector, Deck, Effect, EffectRule, EventFields, IssueCategory, RawEvent, Severity, ValidationIssue, NPC, events_from_json, load_effect_rules, parse_asset, parse_asset_entries, write_vec_sep },
+    error::ValidationError,
+    settings::{ indent, settings, ConnectorStyle, OutputFormat, PagerChoice },
+    yaml::{ describe_class_id, Field, MONOBEHAVIOUR_CLASS_ID, UnityAsset, YamlError },
+    watch::AssetWatcher,
+    Args,
 };
 
 use std::{
+    cmp::Reverse,
     collections::{BTreeMap, BTreeSet},
+    env,
     error::Error,
     fmt::Display, 
-    fs::File,
-    path::PathBuf, 
+    fs::{ self, File },
+    io::{ self, BufReader, BufWriter, Write },
+    mem,
+    path::{ Path, PathBuf }, 
+    process::{ self, Stdio },
     str::FromStr, 
+    time::Instant,
 };
 
+/// An interactive command that couldn't be carried out. Unless it is `fatal`, the session carries
+/// on and the user is asked again.
 #[derive(Debug, Clone)]
-struct CommandError(String);
+struct CommandError {
+    message: String,
+    fatal: bool,
+}
+
+impl CommandError {
+    /// An error that should end the session, such as losing the terminal.
+    fn fatal(message: impl Into<String>) -> Self {
+        CommandError { message: message.into(), fatal: true }
+    }
+}
 
 impl Display for CommandError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        <String as Display>::fmt(&self.0, f)
+        <String as Display>::fmt(&self.message, f)
     }
 }
 
@@ -31,26 +59,135 @@ impl Error for CommandError {}
 
 impl From<String> for CommandError {
     fn from(value: String) -> Self {
-        CommandError(value)
+        CommandError { message: value, fatal: false }
     }
 }
 
 impl From<&str> for CommandError {
     fn from(value: &str) -> Self {
-        CommandError(value.to_owned())
+        value.to_owned().into()
+    }
+}
+
+impl From<Box<dyn Error>> for CommandError {
+    fn from(value: Box<dyn Error>) -> Self {
+        if value.is::<io::Error>() {
+            CommandError::fatal(value.to_string())
+        } else {
+            value.to_string().into()
+        }
     }
 }
 
 impl From<InquireError> for CommandError {
     fn from(value: InquireError) -> Self {
-        value.to_string().into()
+        match value {
+            // Escape only gives up on the current prompt
+            InquireError::OperationCanceled => value.to_string().into(),
+            _ => CommandError::fatal(value.to_string()),
+        }
+    }
+}
+
+/// The `Display` names of every value of `T`, for listing the valid choices.
+fn choices<T: IntoEnumIterator + Display>() -> String {
+    T::iter().map(|value| value.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Warn if an asset isn't a `MonoBehaviour`, as its fields may not mean what they're read as.
+fn warn_unexpected_class(path: &Path, class_id: Option<u64>) {
+    if class_id != Some(MONOBEHAVIOUR_CLASS_ID) {
+        log::warn!("{}: expected a MonoBehaviour asset, but its Unity class is {}", path.display(), describe_class_id(class_id));
+    }
+}
+
+/// Print `text`, through a pager if `--pager` asks for one and the text is long enough.
+fn show(text: &str) {
+    let use_pager = match settings().pager {
+        PagerChoice::Never => false,
+        PagerChoice::Always => true,
+        PagerChoice::Auto => settings().terminal_height.is_some_and(|height| text.lines().count() >= height),
+    };
+    if use_pager {
+        match page(text) {
+            Ok(()) => return,
+            Err(err) => log::warn!("Couldn't run the pager, printing directly instead: {}", err),
+        }
+    }
+    print!("{}", text);
+}
+
+/// The command named by the environment variable `var`, which may include arguments, or
+/// `default` if it isn't set.
+fn command_from_env(var: &str, default: &str) -> process::Command {
+    let command = env::var(var).unwrap_or_else(|_| default.to_owned());
+    let mut words = command.split_whitespace();
+    let mut command = process::Command::new(words.next().unwrap_or(default));
+    command.args(words);
+    command
+}
+
+/// Print the absolute path of `path`, and open it in $EDITOR (or `vi`) if `--editor` was given.
+fn show_source(path: &Path) -> Result<(), CommandError> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+    println!("{}", path.display());
+    if settings().open_in_editor {
+        let status = command_from_env("EDITOR", "vi")
+            .arg(&path)
+            .status()
+            .map_err(|err| format!("Couldn't run the editor: {}", err))?;
+        if !status.success() {
+            return Err(format!("The editor exited with {}", status).into());
+        }
+    }
+    Ok(())
+}
+
+/// Show `text` in $PAGER, or `less -R` if it isn't set, waiting for the pager to exit.
+fn page(text: &str) -> io::Result<()> {
+    let mut child = command_from_env("PAGER", "less -R")
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager may be quit before reading everything, which isn't a problem
+        let _ = stdin.write_all(text.as_bytes());
     }
+    child.wait()?;
+    Ok(())
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, EnumIter)]
 enum Command {
     ViewNPC,
+    ViewNPCByGuid,
     ViewEvent,
+    DuplicateDecks,
+    CheckReferences,
+    ListNPCGuids,
+    QueryCards,
+    TraceCard,
+    Validate,
+    Stats,
+    DifficultyDistribution,
+    EventExtremes,
+    NPCComplexityRanking,
+    RepeatLast,
+    History,
+    Legend,
     Quit,
 }
 
@@ -60,6 +197,20 @@ impl Display for Command {
         write!(f, "{}", match self {
             ViewEvent => "view event",
             ViewNPC => "view npc",
+            ViewNPCByGuid => "view npc by guid",
+            DuplicateDecks => "find shared override decks",
+            CheckReferences => "check npc references",
+            ListNPCGuids => "npc guids",
+            QueryCards => "find cards",
+            TraceCard => "trace a card",
+            Validate => "validate",
+            Stats => "stats",
+            DifficultyDistribution => "concord and discord distribution",
+            EventExtremes => "shortest and longest events",
+            NPCComplexityRanking => "npcs by deck complexity",
+            RepeatLast => "repeat last",
+            History => "history",
+            Legend => "legend",
             Quit => "quit",
         })
     }
@@ -73,8 +224,22 @@ impl FromStr for Command {
         Ok(match s.to_lowercase().as_str() {
             "view event" => ViewEvent,
             "view npc" => ViewNPC,
+            "view npc by guid" => ViewNPCByGuid,
+            "find shared override decks" => DuplicateDecks,
+            "check npc references" => CheckReferences,
+            "npc guids" => ListNPCGuids,
+            "find cards" => QueryCards,
+            "trace a card" => TraceCard,
+            "validate" => Validate,
+            "stats" => Stats,
+            "concord and discord distribution" => DifficultyDistribution,
+            "shortest and longest events" => EventExtremes,
+            "npcs by deck complexity" => NPCComplexityRanking,
+            "repeat last" => RepeatLast,
+            "history" => History,
+            "legend" => Legend,
             "quit" => Quit,
-            _ => { return Err(format!("Unknown command `{}`", s).into()); }
+            _ => { return Err(format!("Unknown command `{}`; choose one of: {}", s, choices::<Command>()).into()); }
         })
     }
 }
@@ -84,53 +249,572 @@ impl Command {
         use Command::*;
         match self {
             ViewEvent => {
-                let id: &str = Select::new("Event id:", app.event_map.keys().collect())
-                    .prompt()?;
-                app.state = AppState::Event { id: id.to_owned() };
+                app.state = AppState::Event { id: app.select_event_id()? };
             }
             ViewNPC => {
-                let npc_id: &str = Select::new("NPC Id:", app.npc_guids.right_values().collect())
+                let mut options: Vec<NPCOption> = app.npc_map.values().map(|npc| NPCOption { npc }).collect();
+                options.sort_by(|a, b| a.npc.id.cmp(&b.npc.id));
+                let choice = Select::new("NPC Id:", options)
+                    .with_filter(&|input, option, _, _| option.npc.id.to_lowercase().contains(&input.to_lowercase()))
+                    .prompt()?;
+                app.state = AppState::NPC { id: choice.npc.id.clone() };
+            }
+            ViewNPCByGuid => {
+                let guid = Text::new("NPC Guid:").prompt()?;
+                let npc_id = app.npc_by_guid(guid.trim())?.id.clone();
+                app.state = AppState::NPC { id: npc_id };
+            }
+            DuplicateDecks => { app.print_duplicate_decks(); }
+            CheckReferences => { app.print_reference_check(); }
+            ListNPCGuids => { app.print_npc_guids(); }
+            QueryCards => { app.print_card_query(&prompt_card_query()?); }
+            TraceCard => { app.print_card_trace(&prompt_card()?); }
+            Validate => { app.print_validation(); }
+            Stats => { app.print_stats(); }
+            DifficultyDistribution => { app.print_difficulty_distribution(); }
+            EventExtremes => {
+                let count: usize = CustomType::new("How many of each?")
+                    .with_default(EXTREMES_COUNT)
+                    .with_error_message("Type a whole number")
                     .prompt()?;
-                app.state = AppState::NPC { id: npc_id.to_owned() };
+                app.print_event_extremes(count);
+            }
+            NPCComplexityRanking => { app.print_npc_complexity_ranking(); }
+            RepeatLast => {
+                let Some(entry) = app.history.last().cloned() else {
+                    return Err("Nothing to repeat yet.".into());
+                };
+                entry.run(app)?;
+            }
+            History => {
+                let entries = app.recent_history(HISTORY_LENGTH);
+                if entries.is_empty() {
+                    return Err("Nothing in the history yet.".into());
+                }
+                let entry = Select::new("What would you like to do again?", entries).prompt()?;
+                entry.clone().run(app)?;
+                app.record(entry);
             }
+            Legend => { print_legend(); }
             Quit => { app.state = AppState::Quit; }
         };
         Ok(())
     }
 }
 
+/// The most history entries kept between sessions.
+const HISTORY_LENGTH: usize = 100;
+
+/// How many history entries are offered above the commands in the first menu.
+const RECENT_LENGTH: usize = 5;
+
+/// Something done from the root menu, kept so that it can be done again with "repeat last" or
+/// "history".
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HistoryEntry {
+    /// Viewed an event, which is repeated by going straight back to it.
+    Event(String),
+    /// Viewed an NPC, which is repeated by going straight back to it.
+    #[allow(clippy::upper_case_acronyms)]
+    NPC(String),
+    Command(Command),
+}
+
+impl Display for HistoryEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistoryEntry::Event(id) => write!(f, "view event {}", id),
+            HistoryEntry::NPC(id) => write!(f, "view npc {}", id),
+            HistoryEntry::Command(command) => write!(f, "{}", command),
+        }
+    }
+}
+
+impl FromStr for HistoryEntry {
+    type Err = CommandError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Commands first, since `view npc by guid` would otherwise look like an NPC id
+        if let Ok(command) = s.parse() {
+            return Ok(HistoryEntry::Command(command));
+        }
+        if let Some(id) = s.strip_prefix("view event ") {
+            return Ok(HistoryEntry::Event(id.to_owned()));
+        }
+        if let Some(id) = s.strip_prefix("view npc ") {
+            return Ok(HistoryEntry::NPC(id.to_owned()));
+        }
+        Err(format!("Unknown history entry `{}`", s).into())
+    }
+}
+
+impl HistoryEntry {
+    /// What to record after `command` was run from the root menu and left the app in `state`.
+    fn after(command: Command, state: &AppState) -> Option<Self> {
+        match (command, state) {
+            (Command::RepeatLast | Command::History | Command::Quit, _) => None,
+            (_, AppState::Event { id }) => Some(HistoryEntry::Event(id.clone())),
+            (_, AppState::NPC { id }) => Some(HistoryEntry::NPC(id.clone())),
+            (command, _) => Some(HistoryEntry::Command(command)),
+        }
+    }
+
+    fn run(self, app: &mut App) -> Result<(), CommandError> {
+        match self {
+            HistoryEntry::Event(id) if app.event_map.contains_key(&id) => app.state = AppState::Event { id },
+            HistoryEntry::NPC(id) if app.npc_guids.contains_right(&id) => app.state = AppState::NPC { id },
+            HistoryEntry::Event(id) | HistoryEntry::NPC(id) => return Err(format!("`{}` isn't in the loaded data.", id).into()),
+            HistoryEntry::Command(command) => command.run(app)?,
+        }
+        Ok(())
+    }
+}
+
+/// A choice in the first menu: one of the most recent history entries, which are listed above the
+/// commands so that pressing up recalls them, or a command.
+enum RootChoice {
+    Recent(HistoryEntry),
+    Command(Command),
+}
+
+impl Display for RootChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RootChoice::Recent(entry) => write!(f, "{} (recent)", entry),
+            RootChoice::Command(command) => write!(f, "{}", command),
+        }
+    }
+}
+
+/// Where the history is kept between sessions: $SOTS_HISTORY_FILE, or a file in the home folder.
+fn history_file() -> Option<PathBuf> {
+    env::var_os("SOTS_HISTORY_FILE").map(PathBuf::from).or_else(|| {
+        let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+        Some(Path::new(&home).join(".sots-event-inspect-history"))
+    })
+}
+
+/// The history saved by earlier sessions, trimming the file to the last `HISTORY_LENGTH` entries.
+fn load_history(path: &Path) -> io::Result<Vec<HistoryEntry>> {
+    let contents = match fs::read_to_string(path) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        contents => contents?,
+    };
+    let mut history: Vec<HistoryEntry> = contents.lines().filter_map(|line| line.parse().ok()).collect();
+    if history.len() > HISTORY_LENGTH {
+        history.drain(..history.len() - HISTORY_LENGTH);
+        let lines: Vec<String> = history.iter().map(|entry| format!("{}\n", entry)).collect();
+        fs::write(path, lines.concat())?;
+    }
+    Ok(history)
+}
+
 #[derive(PartialEq, Eq, Debug, Clone, EnumIter)]
+enum EventSubCommand {
+    ViewAnother,
+    ShowSource,
+    NPCDeck,
+    PlanSegments,
+    Back,
+}
+
+impl Display for EventSubCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use EventSubCommand::*;
+        write!(f, "{}", match self {
+            ViewAnother => "view another event",
+            ShowSource => "show source file",
+            NPCDeck => "npc deck for a cycle",
+            PlanSegments => "plan segments",
+            Back => "back",
+        })
+    }
+}
+
+impl FromStr for EventSubCommand {
+    type Err = CommandError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use EventSubCommand::*;
+        Ok(match s.to_lowercase().as_str() {
+            "view another event" => ViewAnother,
+            "show source file" => ShowSource,
+            "npc deck for a cycle" => NPCDeck,
+            "plan segments" => PlanSegments,
+            "back" => Back,
+            _ => { return Err(format!("Unknown command `{}`; choose one of: {}", s, choices::<EventSubCommand>()).into()); }
+        })
+    }
+}
+
+impl EventSubCommand {
+    fn run(self, app: &mut App) -> Result<(), CommandError> {
+        use EventSubCommand::*;
+        match self {
+            ViewAnother => {
+                app.state = AppState::Event { id: app.select_event_id()? };
+            },
+            ShowSource => {
+                let AppState::Event { id } = &app.state else {
+                    return Err("Not viewing an event.".into());
+                };
+                let Some(source) = app.event_sources.get(id) else {
+                    return Err(format!("Don't know which file event {} came from.", id).into());
+                };
+                show_source(source)?;
+            },
+            NPCDeck => {
+                let AppState::Event { id } = &app.state else {
+                    return Err("Not viewing an event.".into());
+                };
+                app.print_inherited_deck(&app.event_map[id])?;
+            },
+            PlanSegments => {
+                let AppState::Event { id } = &app.state else {
+                    return Err("Not viewing an event.".into());
+                };
+                app.print_segment_plan(&app.event_map[id])?;
+            },
+            Back => {
+                app.state = AppState::Root;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Print every connector type and effect with the symbols used for it, built from the enums
+/// themselves so it always matches what is shown elsewhere.
+fn print_legend() {
+    println!("Connectors:");
+    println!("{}glyph letter  ascii  name", indent(1));
+    for connect_type in ConnectType::iter() {
+        println!("{}{:<6}{:<8}{:<7}{}", indent(1), connect_type.glyph(), connect_type.letter(), connect_type.ascii(), connect_type.name());
+    }
+    println!("Effects:");
+    println!("{}glyph letter  id    name", indent(1));
+    for effect in Effect::iter().filter(|effect| *effect != Effect::None) {
+        println!("{}{:<6}{:<8}{:<6}{}", indent(1), effect.glyph(), effect.letter(), effect.id(), effect.name());
+    }
+}
+
+/// Print a single `.asset` file: the NPC it describes, or else the events in it. With `count_only`,
+/// only count the events. Decks stored in other assets can't be shown, since their guids can only
+/// be looked up by loading the whole folder.
+pub fn print_asset_file(path: &Path, count_only: bool) -> Result<(), Box<dyn Error>> {
+    let unresolvable = |guid: &str| -> Result<Field, Box<dyn Error + Send + Sync>> {
+        Err(format!("the asset with guid `{}` can only be found when loading a folder.", guid).into())
+    };
+    let npc = NPC::load_asset(path, &unresolvable).map_err(|err| err as Box<dyn Error>)?;
+    if let Some(npc) = npc {
+        if count_only {
+            println!("NPC: {}", npc.id);
+            println!("Cards in NPC decks: {}", npc.decks.iter().map(Deck::card_count).sum::<usize>());
+        } else {
+            npc.print_details();
+            npc.print_all_decks()?;
+        }
+        return Ok(());
+    }
+    if count_only {
+        return print_event_file_counts(path);
+    }
+    for event in parse_asset(&fs::read_to_string(path)?)? {
+        println!("Event - {}", event);
+    }
+    Ok(())
+}
+
+/// Print counts for a single `event_data.asset` file, without loading any NPCs.
+pub fn print_event_file_counts(path: &Path) -> Result<(), Box<dyn Error>> {
+    let events = parse_asset(&fs::read_to_string(path)?)?;
+    print_event_counts(events.iter());
+    Ok(())
+}
+
+/// Print counts for a set of events, returning the number of cards in their override decks.
+fn print_event_counts<'a>(events: impl Iterator<Item = &'a RawEvent>) -> usize {
+    let (mut total, mut overrides, mut cards) = (0, 0, 0);
+    for event in events {
+        total += 1;
+        if let Some(deck) = &event.deck {
+            overrides += 1;
+            cards += deck.card_count();
+        }
+    }
+    println!("Events: {}", total);
+    println!("Events with an override deck: {}", overrides);
+    println!("Cards in override decks: {}", cards);
+    cards
+}
+
+/// An NPC to choose from a `Select`, shown with a preview of its hand and decks to tell apart
+/// NPCs with similar ids.
+struct NPCOption<'a> {
+    npc: &'a NPC,
+}
+
+impl Display for NPCOption<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let deck_sizes: Vec<String> = self.npc.decks.iter().skip(1)
+            .map(|deck| deck.card_count().to_string())
+            .collect();
+        write!(f, "{}  (hand {}, deck sizes {})", self.npc.id, self.npc.hand_size, deck_sizes.join("/"))
+    }
+}
+
+/// Suggests and completes event ids in a `Text` prompt.
+#[derive(Debug, Clone)]
+struct EventIdCompleter {
+    ids: Vec<String>,
+}
+
+impl EventIdCompleter {
+    fn matches(&self, input: &str) -> Vec<String> {
+        let input = input.to_lowercase();
+        self.ids.iter()
+            .filter(|id| id.to_lowercase().contains(&input))
+            .cloned()
+            .collect()
+    }
+
+    fn validate(&self, input: &str) -> Validation {
+        if self.ids.iter().any(|id| id == input) {
+            return Validation::Valid;
+        }
+        let suggestions = self.matches(input);
+        Validation::Invalid(match suggestions.len() {
+            0 => format!("No event has an id containing `{}`", input),
+            1..=5 => format!("No event `{}`; did you mean {}?", input, suggestions.join(", ")),
+            n => format!("No event `{}`; {} ids contain it", input, n),
+        }.into())
+    }
+}
+
+impl Autocomplete for EventIdCompleter {
+    fn get_suggestions(&mut self, input: &str) -> Result<Vec<String>, CustomUserError> {
+        Ok(self.matches(input))
+    }
+
+    fn get_completion(&mut self, input: &str, highlighted_suggestion: Option<String>) -> Result<Replacement, CustomUserError> {
+        if highlighted_suggestion.is_some() {
+            return Ok(highlighted_suggestion);
+        }
+        // Complete as far as every id starting with the input agrees
+        let mut candidates = self.ids.iter().filter(|id| id.starts_with(input));
+        let Some(first) = candidates.next() else { return Ok(None); };
+        let prefix = candidates.fold(first.as_str(), |prefix, id| {
+            let common = prefix.char_indices()
+                .zip(id.chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(prefix.len(), |((i, _), _)| i);
+            &prefix[..common]
+        });
+        Ok((prefix.len() > input.len()).then(|| prefix.to_owned()))
+    }
+}
+
+/// The number of single character insertions, deletions or substitutions to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The name shown for the NPC of an event whose guid couldn't be resolved.
+fn unknown_npc_id(guid: &str) -> String {
+    format!("<unknown:{}>", guid)
+}
+
+/// Ask for a connector, typed in any connector style. `empty` says what leaving it empty means,
+/// if that is allowed.
+fn prompt_connector(message: &str, empty: Option<&str>) -> Result<Connector, CommandError> {
+    // Give the example and the active style's symbols first, in the way connectors are shown
+    let style = settings().connector_style;
+    let mut example = Connector::default();
+    example.extend([ConnectType::Circle, ConnectType::Triangle]);
+    let mut help = format!("Type the symbols, e.g. {}", example);
+    if let Some(empty) = empty {
+        help.push_str(&format!(", or leave empty for {}", empty));
+    }
+    let mut styles = vec![style];
+    styles.extend(ConnectorStyle::value_variants().iter().filter(|other| **other != style));
+    let symbols: Vec<String> = styles.into_iter()
+        .map(|style| ConnectType::iter().map(|connect_type| connect_type.symbol(style)).collect::<Vec<_>>().join(" "))
+        .collect();
+    Ok(CustomType::new(message)
+        .with_help_message(&help)
+        .with_error_message(&format!("Unknown symbol; use {}", symbols.join(", or ")))
+        .prompt()?)
+}
+
+/// Ask for each condition of a `CardQuery`, leaving out any the user skips.
+fn prompt_card_query() -> Result<CardQuery, CommandError> {
+    let prompt_condition = |message: &str| -> Result<Option<Connector>, CommandError> {
+        let connector = prompt_connector(message, Some("any"))?;
+        Ok((!connector.is_empty()).then_some(connector))
+    };
+    let input = prompt_condition("Input includes:")?;
+    let output = prompt_condition("Output includes:")?;
+
+    let mut effect_names = vec!["any"];
+    effect_names.extend(Effect::iter().filter(|effect| *effect != Effect::None).map(|effect| effect.name()));
+    let effect_name = Select::new("Effect:", effect_names).prompt()?;
+    let effect = Effect::iter().find(|effect| effect.name() == effect_name);
+
+    Ok(CardQuery { input, output, effect })
+}
+
+/// Ask for every part of a single card, rather than conditions on it like `prompt_card_query`.
+fn prompt_card() -> Result<Card, CommandError> {
+    let input = prompt_connector("Input:", Some("none"))?;
+    let output = prompt_connector("Output:", Some("none"))?;
+
+    let mut effect_names = vec!["none"];
+    effect_names.extend(Effect::iter().filter(|effect| *effect != Effect::None).map(|effect| effect.name()));
+    let effect_name = Select::new("Effect:", effect_names).prompt()?;
+    let effect = Effect::iter().find(|effect| effect.name() == effect_name).unwrap_or(Effect::None);
+
+    Ok(Card::new(input, output, effect))
+}
+
+fn select_cycle(message: &str, npc: &NPC) -> Result<usize, CommandError> {
+    match npc.cycle_count() {
+        // Only the fallback deck, which is cycle 0
+        0 => Ok(0),
+        1 => Ok(1),
+        count => Ok(Select::new(message, (1..=count).collect()).prompt()?),
+    }
+}
+
+/// The most `#`s in a histogram bar.
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// How many of the shortest and longest events to show, unless told otherwise.
+const EXTREMES_COUNT: usize = 5;
+
+/// A connector balance at or below this is flagged as a likely bottleneck.
+const BOTTLENECK_BALANCE: i64 = -2;
+
+#[derive(PartialEq, Eq, Debug, Clone)]
 enum DeckSubCommand {
-    Deck1,
-    Deck2,
-    Deck3,
-    Deck4,
-    Deck5,
+    Cycle(usize),
     AllDecks,
     FallbackDeck,
+    DiffFromFallback,
+    PlayableAfter,
+    PathBetween,
+    WithEffect,
+    Balance,
+    ExpectedHand,
+    Reachability,
+    ExportRaw,
 }
 
 impl DeckSubCommand {
+    /// The choices available for `npc`: one per cycle it has a deck for, then the other views.
+    fn options(npc: &NPC) -> Vec<Self> {
+        use DeckSubCommand::*;
+        (1..=npc.cycle_count()).map(Cycle)
+            .chain([AllDecks, FallbackDeck, DiffFromFallback, PlayableAfter, PathBetween, WithEffect, Balance, ExpectedHand, Reachability, ExportRaw])
+            .collect()
+    }
+
     fn cycle(&self) -> Option<usize> {
         use DeckSubCommand::*;
         match self {
-            Deck1 => Some(1),
-            Deck2 => Some(2),
-            Deck3 => Some(3),
-            Deck4 => Some(4),
-            Deck5 => Some(5),
+            Cycle(cycle) => Some(*cycle),
             FallbackDeck => None,
             AllDecks => None,
+            DiffFromFallback => None,
+            PlayableAfter => None,
+            PathBetween => None,
+            WithEffect => None,
+            Balance => None,
+            ExpectedHand => None,
+            Reachability => None,
+            ExportRaw => None,
         }
     }
 
     fn run(self, npc: &NPC) -> Result<(), CommandError> {
         if self == DeckSubCommand::AllDecks {
-            npc.print_all_decks();
+            show(&npc.all_decks_text()?);
         } else if self == DeckSubCommand::FallbackDeck {
-            npc.print_fallback_deck();
+            npc.print_fallback_deck()?;
+        } else if self == DeckSubCommand::DiffFromFallback {
+            let cycle = select_cycle("Which cycle do you want to compare against the fallback deck?", npc)?;
+            npc.print_deck_diff(cycle)?;
+        } else if self == DeckSubCommand::PlayableAfter {
+            let cycle = select_cycle("Which cycle's deck do you want to play from?", npc)?;
+            let current = prompt_connector("What is the output of the card in play?", None)?;
+            let playable = npc.deck_for_cycle(Some(cycle))?.playable_after(&current);
+            let (i1, i2) = (indent(1), indent(2));
+            println!("{i1}{} card(s) in the cycle {} deck can be played after {}:", playable.len(), cycle, current);
+            playable.iter().for_each(|card| println!("{i2}{}", card));
+        } else if self == DeckSubCommand::PathBetween {
+            let cycle = select_cycle("Which cycle's deck do you want to play from?", npc)?;
+            let prompt_connector = |message: &str| -> Result<Connector, CommandError> {
+                Ok(CustomType::new(message)
+                    .with_help_message("Type the symbols, e.g. ○△ or CT")
+                    .with_error_message("Unknown symbol; use ○ △ □ ◊ 🐾 @ or C T S D P @")
+                    .prompt()?)
+            };
+            let from = prompt_connector("What is the output of the card in play?")?;
+            let to = prompt_connector("Which symbols do you want to end on?")?;
+            let i1 = indent(1);
+            match npc.deck_for_cycle(Some(cycle))?.path_between(&from, &to) {
+                None => println!("{i1}No cards in the cycle {} deck lead from {} to {}", cycle, from, to),
+                Some(path) if path.is_empty() => println!("{i1}{} already has {}", from, to),
+                Some(path) => {
+                    println!("{i1}{} card(s) in the cycle {} deck lead from {} to {}:", path.len(), cycle, from, to);
+                    path.iter().for_each(|card| println!("{}{}", indent(2), card));
+                }
+            }
+        } else if self == DeckSubCommand::WithEffect {
+            let cycle = select_cycle("Which cycle's deck do you want to look through?", npc)?;
+            let effect = Select::new("Which effect?", Effect::iter().filter(|effect| *effect != Effect::None).collect())
+                .prompt()?;
+            let matching = npc.deck_for_cycle(Some(cycle))?.cards_with_effect(&effect);
+            let (i1, i2) = (indent(1), indent(2));
+            println!("{i1}{} card(s) in the cycle {} deck have {}:", matching.len(), cycle, effect);
+            matching.iter().for_each(|card| println!("{i2}{}", card));
+        } else if self == DeckSubCommand::Balance {
+            let cycle = select_cycle("Which cycle's deck do you want to check?", npc)?;
+            let (i1, i2) = (indent(1), indent(2));
+            println!("{i1}Outputs minus inputs for each symbol in the cycle {} deck:", cycle);
+            for (connect_type, balance) in npc.deck_for_cycle(Some(cycle))?.connector_balance() {
+                let note = if balance <= BOTTLENECK_BALANCE { "  (likely bottleneck)" } else { "" };
+                println!("{i2}{} {:>+4}{}", connect_type, balance, note);
+            }
+        } else if self == DeckSubCommand::ExpectedHand {
+            let cycle = select_cycle("Which cycle's deck do you want to draw from?", npc)?;
+            let deck = npc.deck_for_cycle(Some(cycle))?;
+            let (i1, i2) = (indent(1), indent(2));
+            if npc.hand_size as usize >= deck.cards.len() {
+                println!("{i1}{}'s hand of {} holds the whole cycle {} deck of {} card(s), so it will have:", npc.id, npc.hand_size, cycle, deck.cards.len());
+            } else {
+                println!("{i1}Expected symbols in {}'s opening hand of {} from the cycle {} deck:", npc.id, npc.hand_size, cycle);
+            }
+            for (connect_type, expected) in deck.expected_hand_connectors(npc.hand_size) {
+                println!("{i2}{} {:>5.2}", connect_type, expected);
+            }
+        } else if self == DeckSubCommand::Reachability {
+            let cycle = select_cycle("Which cycle's deck do you want to check?", npc)?;
+            show(&format!("{}\n", npc.deck_for_cycle(Some(cycle))?.reachability_table()));
+        } else if self == DeckSubCommand::ExportRaw {
+            let cycle = select_cycle("Which cycle's deck do you want to export?", npc)?;
+            println!("{}", npc.deck_for_cycle(Some(cycle))?.to_asset_yaml());
         } else {
-            npc.print_deck(self.cycle().expect("variant with specific cycle number"));
+            npc.print_deck(self.cycle().expect("variant with specific cycle number"))?;
         }
         Ok(())
     }
@@ -139,14 +823,21 @@ impl DeckSubCommand {
 impl Display for DeckSubCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use DeckSubCommand::*;
+        if let Cycle(cycle) = self {
+            return write!(f, "{}", cycle);
+        }
         write!(f, "{}", match *self {
-            Deck1 => "1",
-            Deck2 => "2",
-            Deck3 => "3",
-            Deck4 => "4",
-            Deck5 => "5",
+            Cycle(_) => unreachable!("handled above"),
             FallbackDeck => "fallback",
             AllDecks => "all",
+            DiffFromFallback => "changes from fallback",
+            PlayableAfter => "playable after a connector",
+            PathBetween => "path between connectors",
+            WithEffect => "cards with an effect",
+            Balance => "connector balance",
+            ExpectedHand => "expected opening hand",
+            Reachability => "which cards can follow which",
+            ExportRaw => "raw asset values",
         })
     }
 }
@@ -158,14 +849,24 @@ impl FromStr for DeckSubCommand {
         use DeckSubCommand::*;
         Ok(
             match s.to_lowercase().as_str() {
-                "1" => Deck1,
-                "2" => Deck2,
-                "3" => Deck3,
-                "4" => Deck4,
-                "5" => Deck5,
                 "fallback" => FallbackDeck,
                 "all" => AllDecks,
-                _ => { return Err("Unknown command".into()); }
+                "changes from fallback" => DiffFromFallback,
+                "playable after a connector" => PlayableAfter,
+                "path between connectors" => PathBetween,
+                "cards with an effect" => WithEffect,
+                "connector balance" => Balance,
+                "expected opening hand" => ExpectedHand,
+                "which cards can follow which" => Reachability,
+                "raw asset values" => ExportRaw,
+                cycle => match cycle.parse() {
+                    Ok(cycle) => Cycle(cycle),
+                    Err(_) => {
+                        return Err(format!("Unknown command `{}`; choose a cycle number, or one of: fallback, all, changes from fallback, \
+                                            playable after a connector, path between connectors, cards with an effect, connector balance, \
+                                            expected opening hand, which cards can follow which, raw asset values", s).into());
+                    }
+                },
             }
         )
     }
@@ -175,6 +876,8 @@ impl FromStr for DeckSubCommand {
 enum NPCSubCommand {
     ViewEvents,
     ViewDecks,
+    ReferenceSheet,
+    ShowSource,
     Back,
 }
 
@@ -184,6 +887,8 @@ impl Display for NPCSubCommand {
         write!(f, "{}", match self {
             ViewEvents => "events",
             ViewDecks => "decks",
+            ReferenceSheet => "reference sheet",
+            ShowSource => "source file",
             Back => "back",
         })
     }
@@ -197,8 +902,10 @@ impl FromStr for NPCSubCommand {
         Ok(match s.to_lowercase().as_str() {
             "events" => ViewEvents,
             "decks" => ViewDecks,
+            "reference sheet" => ReferenceSheet,
+            "source file" => ShowSource,
             "back" => Back,
-            _ => { return Err(format!("Unknown command `{}`", s).into()); }
+            _ => { return Err(format!("Unknown command `{}`; choose one of: {}", s, choices::<NPCSubCommand>()).into()); }
         })
     }
 }
@@ -211,9 +918,16 @@ impl NPCSubCommand {
                 *app_state = AppState::NPCEvents { npc_id: npc.id.clone() };
             },
             ViewDecks => { 
-                let sub_cmd = Select::new("Which cycle do you want the deck for?", DeckSubCommand::iter().collect())
+                let sub_cmd = Select::new("Which cycle do you want the deck for?", DeckSubCommand::options(npc))
                     .prompt()?;
-                sub_cmd.run(&npc)?;
+                sub_cmd.run(npc)?;
+            },
+            ReferenceSheet => { show(&npc.reference_sheet()); },
+            ShowSource => {
+                let Some(source) = &npc.source else {
+                    return Err(format!("Don't know which file {} came from.", npc.id).into());
+                };
+                show_source(source)?;
             },
             Back => {
                 *app_state = AppState::Root;
@@ -223,26 +937,112 @@ impl NPCSubCommand {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 struct Event {
     npc_id: String,
     event: RawEvent,
+    /// The file the event was loaded from, if events were loaded from more than one.
+    source: Option<PathBuf>,
+    /// The number of discords that make the event's NPC mad, if the NPC was loaded.
+    mad_threshold: Option<u8>,
 }
 
 impl Display for Event {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let i1 = indent(1);
         writeln!(f, "{}:", self.event.id)?;
-        writeln!(f, "\tNPC: {}", self.npc_id)?;
-        writeln!(f, "\tNum Concord: {}", self.event.sequence_count)?;
-        writeln!(f, "\tNum Discord: {}", self.event.strike_count)?;
-        write!(f, "\tSequence Lengths: ")?;
+        writeln!(f, "{i1}NPC: {}", self.npc_id)?;
+        if let Some(source) = &self.source {
+            writeln!(f, "{i1}Source: {}", source.display())?;
+        }
+        writeln!(f, "{i1}Num Concord: {}", self.event.sequence_count)?;
+        writeln!(f, "{i1}Num Discord: {}", self.event.strike_count)?;
+        if let Some(mad_threshold) = self.mad_threshold.filter(|_| settings().show_notes) {
+            write!(f, "{i1}{} becomes mad at {} discords; this event allows {}", self.npc_id, mad_threshold, self.event.strike_count)?;
+            if self.event.strike_count >= mad_threshold {
+                write!(f, ", so it can end with {} mad", self.npc_id)?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "{i1}Difficulty: {}", self.event.difficulty())?;
+        write!(f, "{i1}Sequence Lengths: ")?;
         write_vec_sep(&self.event.sequence_lengths, ", ", f)?;
+        writeln!(f)?;
         if let Some(deck) = &self.event.deck {
-            writeln!(f, "\n\tOverrides NPC deck with:")?;
-            writeln!(f, "{}", deck)
-        } else {
-            writeln!(f, "\n\tUses default deck for this cycle; see NPC data.")
+            writeln!(f, "{i1}Overrides NPC deck with:")?;
+            writeln!(f, "{}", deck)?;
+        } else if settings().show_inheritance_note {
+            writeln!(f, "{i1}Uses default deck for this cycle; see NPC data.")?;
         }
+        if settings().show_raw_fields {
+            writeln!(f, "{i1}Raw sequence: {}", self.event.raw_sequence)?;
+            writeln!(f, "{i1}Raw overrideDeck: {}", self.event.raw_override_deck)?;
+        }
+        Ok(())
+    }
+}
+
+/// Print which of `newer` were added, removed or changed compared to `older` under `title`, with
+/// the changes `describe` finds for each.
+fn print_changes<T>(title: &str, newer: &BTreeMap<String, &T>, older: &BTreeMap<String, &T>, describe: impl Fn(&T, &T) -> Vec<String>) {
+    let (i1, i2, i3) = (indent(1), indent(2), indent(3));
+    let added: Vec<&str> = newer.keys().filter(|id| !older.contains_key(*id)).map(String::as_str).collect();
+    let removed: Vec<&str> = older.keys().filter(|id| !newer.contains_key(*id)).map(String::as_str).collect();
+    let changed: Vec<(&str, Vec<String>)> = newer.iter()
+        .filter_map(|(id, new)| Some((id.as_str(), describe(new, older.get(id)?))))
+        .filter(|(_, changes)| !changes.is_empty())
+        .collect();
+
+    println!("{}:", title);
+    if added.is_empty() && removed.is_empty() && changed.is_empty() {
+        println!("{i1}No differences.");
+        return;
+    }
+    for (label, ids) in [("Added", added), ("Removed", removed)] {
+        if !ids.is_empty() {
+            println!("{i1}{} ({}):", label, ids.len());
+            ids.iter().for_each(|id| println!("{i2}{}", id));
+        }
+    }
+    if !changed.is_empty() {
+        println!("{i1}Changed ({}):", changed.len());
+        for (id, changes) in changed {
+            println!("{i2}{}:", id);
+            changes.iter().for_each(|change| println!("{i3}{}", change));
+        }
+    }
+}
+
+/// The events as they are exported, with only `fields` in the order given.
+fn project_events(events: &[&Event], fields: &EventFields) -> Result<Vec<serde_json::Value>, serde_json::Error> {
+    events.iter().map(|event| event.project(fields)).collect()
+}
+
+impl Event {
+    /// The event as it is exported, with only `fields` in the order given.
+    fn project(&self, fields: &EventFields) -> Result<serde_json::Value, serde_json::Error> {
+        let mut exported = serde_json::to_value(&self.event)?;
+        exported["npc_id"] = self.npc_id.clone().into();
+        Ok(fields.0.iter()
+            .map(|field| (field.clone(), exported[field].take()))
+            .collect::<serde_json::Map<_, _>>()
+            .into())
+    }
+
+    /// A one-line summary for `--compact`: `id | npc | Concord/Discord | lengths`, followed by
+    /// `| override` if the event overrides its NPC's deck.
+    fn compact(&self) -> String {
+        let lengths: Vec<String> = self.event.sequence_lengths.iter().map(u8::to_string).collect();
+        let mut line = format!("{} | {} | C{}/D{} | {}",
+            self.event.id,
+            self.npc_id,
+            self.event.sequence_count,
+            self.event.strike_count,
+            lengths.join(","));
+        if self.event.deck.is_some() {
+            line.push_str(" | override");
+        }
+        line
     }
 }
 
@@ -250,6 +1050,7 @@ impl Display for Event {
 enum AppState {
     Root,
     Event { id: String },
+    #[allow(clippy::upper_case_acronyms)]
     NPC { id: String },
     NPCEvents { npc_id: String },
     Quit,
@@ -261,21 +1062,151 @@ pub struct App {
     npc_map: BTreeMap<String, NPC>,
     npc_guids: BiBTreeMap<String, String>, // (Guid, NPC id)
     npc_events: BTreeMap<String, BTreeSet<String>>, // (NPC id, Set of Event ids)
+    unresolved_events: BTreeMap<String, String>, // (Event id, unknown NPC Guid)
+    event_sources: BTreeMap<String, PathBuf>, // (Event id, file it was loaded from)
+    duplicate_events: Vec<(String, PathBuf, PathBuf)>, // (Event id, file it was kept from, file with the duplicate)
+    duplicate_assets: Vec<(String, PathBuf, PathBuf)>, // (What they share, asset that was kept, asset that was skipped)
+    load_failures: Vec<(String, Box<dyn Error>)>, // (What was skipped, why)
+    effect_rules: Vec<EffectRule>,
+    history: Vec<HistoryEntry>,
+    watching: Option<(AssetWatcher, PathBuf)>, // (Watcher, folder to reload from when it sees a change)
     state: AppState,
+    args: Args,
+}
+
+/// The rules from `--effect-rules`, or the default ones.
+fn effect_rules(args: &Args) -> Result<Vec<EffectRule>, Box<dyn Error>> {
+    match &args.effect_rules {
+        Some(path) => load_effect_rules(path),
+        None => Ok(EffectRule::defaults()),
+    }
+}
+
+/// Bumped whenever the layout of `Cache` or the types in it change, so that old caches are rejected
+/// rather than misread.
+const CACHE_VERSION: u32 = 3;
+
+/// Everything loaded from the assets, as written by `--save-cache`.
+#[derive(Serialize)]
+struct CacheRef<'a> {
+    event_map: &'a BTreeMap<String, Event>,
+    npc_map: &'a BTreeMap<String, NPC>,
+    npc_guids: &'a BiBTreeMap<String, String>,
+    npc_events: &'a BTreeMap<String, BTreeSet<String>>,
+    unresolved_events: &'a BTreeMap<String, String>,
+    event_sources: &'a BTreeMap<String, PathBuf>,
+    duplicate_events: &'a Vec<(String, PathBuf, PathBuf)>,
+    duplicate_assets: &'a Vec<(String, PathBuf, PathBuf)>,
+    // Left out when the events and NPCs themselves are serialized, so kept separately
+    raw_event_fields: BTreeMap<&'a str, (&'a str, u64)>, // (Event id, (raw sequence, raw overrideDeck))
+    npc_sources: BTreeMap<&'a str, &'a Path>, // (Guid, asset path)
+}
+
+/// The owned form of `CacheRef`, as read by `--load-cache`.
+#[derive(Deserialize)]
+struct Cache {
+    event_map: BTreeMap<String, Event>,
+    npc_map: BTreeMap<String, NPC>,
+    npc_guids: BiBTreeMap<String, String>,
+    npc_events: BTreeMap<String, BTreeSet<String>>,
+    unresolved_events: BTreeMap<String, String>,
+    event_sources: BTreeMap<String, PathBuf>,
+    duplicate_events: Vec<(String, PathBuf, PathBuf)>,
+    duplicate_assets: Vec<(String, PathBuf, PathBuf)>,
+    raw_event_fields: BTreeMap<String, (String, u64)>,
+    npc_sources: BTreeMap<String, PathBuf>,
 }
 
 impl App {
-    pub fn new(args: Args) -> Result<Self, Box<dyn Error>> {
+    /// Load the data saved by `save_cache`, instead of parsing the assets.
+    pub fn from_cache(args: Args, path: &Path) -> Result<Self, Box<dyn Error>> {
+        let start = Instant::now();
+        let mut reader = BufReader::new(File::open(path)?);
+        let version: u32 = bincode::deserialize_from(&mut reader)?;
+        if version != CACHE_VERSION {
+            return Err(format!("{} was saved by a different version of this tool; save it again with --save-cache.", path.display()).into());
+        }
+        let mut cache: Cache = bincode::deserialize_from(reader)?;
+        for (id, (raw_sequence, raw_override_deck)) in cache.raw_event_fields {
+            if let Some(event) = cache.event_map.get_mut(&id) {
+                event.event.raw_sequence = raw_sequence;
+                event.event.raw_override_deck = raw_override_deck;
+            }
+        }
+        for (guid, source) in cache.npc_sources {
+            if let Some(npc) = cache.npc_map.get_mut(&guid) {
+                npc.source = Some(source);
+            }
+        }
+        log::info!("Loaded {} in {}ms", path.display(), start.elapsed().as_millis());
+
+        Ok(Self {
+            event_map: cache.event_map,
+            npc_map: cache.npc_map,
+            npc_guids: cache.npc_guids,
+            npc_events: cache.npc_events,
+            unresolved_events: cache.unresolved_events,
+            event_sources: cache.event_sources,
+            duplicate_events: cache.duplicate_events,
+            duplicate_assets: cache.duplicate_assets,
+            load_failures: Vec::new(),
+            effect_rules: effect_rules(&args)?,
+            history: Vec::new(),
+            watching: None,
+            state: AppState::Root,
+            args,
+        })
+    }
+
+    /// Save everything that was loaded to `path`, to be read back with `from_cache`.
+    pub fn save_cache(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let cache = CacheRef {
+            event_map: &self.event_map,
+            npc_map: &self.npc_map,
+            npc_guids: &self.npc_guids,
+            npc_events: &self.npc_events,
+            unresolved_events: &self.unresolved_events,
+            event_sources: &self.event_sources,
+            duplicate_events: &self.duplicate_events,
+            duplicate_assets: &self.duplicate_assets,
+            raw_event_fields: self.event_map.iter()
+                .map(|(id, event)| (id.as_str(), (event.event.raw_sequence.as_str(), event.event.raw_override_deck)))
+                .collect(),
+            npc_sources: self.npc_map.iter()
+                .filter_map(|(guid, npc)| Some((guid.as_str(), npc.source.as_deref()?)))
+                .collect(),
+        };
+        let mut writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(&mut writer, &CACHE_VERSION)?;
+        bincode::serialize_into(&mut writer, &cache)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn new(args: Args, path: &Path) -> Result<Self, Box<dyn Error>> {
         let mut out = Self {
             event_map: BTreeMap::new(),
             npc_map: BTreeMap::new(),
             npc_guids: BiBTreeMap::new(),
             npc_events: BTreeMap::new(),
+            unresolved_events: BTreeMap::new(),
+            event_sources: BTreeMap::new(),
+            duplicate_events: Vec::new(),
+            duplicate_assets: Vec::new(),
+            load_failures: Vec::new(),
+            effect_rules: effect_rules(&args)?,
+            history: Vec::new(),
+            watching: None,
             state: AppState::Root,
+            args,
         };
 
-        out.build_npc_maps(&args.path)?;
-        out.parse_event_data(args.path)?;
+        if !out.args.only_events {
+            out.build_npc_maps(path)?;
+        }
+        if !out.args.only_npc_decks {
+            out.parse_event_data(path)?;
+        }
         
         Ok(out)
     }
@@ -284,125 +1215,1083 @@ impl App {
         self.state != AppState::Quit
     }
 
-    pub fn run(&mut self) -> Result<(), Box<dyn Error>> {
-        while self.is_running() {
-            use AppState::*;
-            match &self.state {
-                Root => {
-                    let cmd: Command = Select::new("What would you like to do?", Command::iter().collect())
-                        .prompt()?;
-                    cmd.run(self)?;
-                },
-                Event { id } => {
-                    let Some(event) = self.event_map.get(id) else {
-                        return Err("Select somehow returned an invalid event id.".into());
-                    };
-                    println!("Event - {}", event);
-                    self.state = Root;
-                },
-                NPC { id } => {
-                    let Some(guid) = self.npc_guids.get_by_right(id) else {
-                        return Err("Select somehow returned an invalid NPC Id.".into());
-                    };
-                    let Some(npc) = self.npc_map.get(guid) else {
-                        return Err("NPC Id was mapped to an invalid NPC GUID.".into());
-                    };
-                    npc.print_details();
-                    let sub_cmd = Select::new(&format!("What would you like to know about {}?", npc.id), NPCSubCommand::iter().collect())
-                        .prompt()?;
-                    sub_cmd.run(&mut self.state, npc)?;
-                },
-                NPCEvents { npc_id } => {
-                    let Some(event_ids) = self.npc_events.get(npc_id) else {
-                        return Err("Somehow ended up with an invalid NPC Id.".into());
-                    };
-                    println!("{} has the following events:", npc_id);
-                    event_ids.iter().for_each(|e| {
-                        println!("\t{}", e);
-                    });
-                    let inspect = Confirm::new("Would you like to inspect one of these events?").prompt()?;
-                    if inspect {
-                        let mut options = event_ids.clone();
-                        options.insert("cancel".to_owned());
-                        let event_id = Select::new("Which event would you like to inspect?", options.iter().collect())
-                            .prompt()?;
-                        if let Some(event) = self.event_map.get(event_id) {
-                            println!("Event - {}", event);
-                        } else if event_id == "cancel" {
-                            println!("Cancelled.");
-                        } else {
-                            return Err("Select somehow returned an invalid event id.".into());
-                        };
-                    }
-                    self.state = NPC { id: npc_id.clone() };
-                },
-                Quit => { unreachable!("Loop should end as soon as we enter the AppState::Quit state"); }
-            }
-        }
+    /// Load the data from `path` again whenever it changes, checking before each prompt.
+    pub fn watch(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        self.watching = Some((AssetWatcher::new(path)?, path.to_owned()));
         Ok(())
     }
 
-    fn parse_event_data(&mut self, mut folder_path: PathBuf) -> Result<(), Box<dyn Error>> {
-        folder_path.push("event_data.asset");
-        let file = File::open(folder_path)?;
-        let yaml: Field = serde_yaml::from_reader(file)?;
-        
-        let Field::Struct(data_map) = yaml else { return Err("Root isn't a map".into()); };
-        let ref_data_map = &data_map;
-        field_get!(let monobehaviour: Struct = ref_data_map.MonoBehaviour);
-        field_get!(let events: List = monobehaviour.data);
-
-
-        self.event_map = events.iter()
-            .map(|field| {
-                let raw = RawEvent::try_from(field)?;
-                if let Some(npc_id) = self.npc_guids.get_by_left(&raw.npc_guid) {
-                    // Insert to relevant npc_events set
-                    let Some(event_set) = self.npc_events.get_mut(npc_id) else {
-                        return Err(format!("NPC {} somehow wasn't added to the npc_events map", npc_id).into());
-                    };
-                    event_set.insert(raw.id.clone());
-
-                    // Create the actual Event struct for the event_map
-                    Ok((raw.id.clone(), Event {
-                        npc_id: npc_id.clone(),
-                        event: raw
-                    }))
+    /// If the watched assets have changed, load them again, staying on the current event or NPC
+    /// if it still exists. If they can't be loaded, carry on with the old data.
+    fn reload_if_changed(&mut self) {
+        let Some((watcher, path)) = &self.watching else { return; };
+        if !watcher.has_changed() {
+            return;
+        }
+        match App::new(self.args.clone(), path) {
+            Ok(mut reloaded) => {
+                let still_exists = match &self.state {
+                    AppState::Event { id } => reloaded.event_map.contains_key(id),
+                    AppState::NPC { id } | AppState::NPCEvents { npc_id: id } => reloaded.npc_guids.contains_right(id),
+                    AppState::Root | AppState::Quit => true,
+                };
+                if still_exists {
+                    reloaded.state = mem::replace(&mut self.state, AppState::Root);
+                }
+                reloaded.watching = self.watching.take();
+                reloaded.history = mem::take(&mut self.history);
+                *self = reloaded;
+                println!("Data reloaded.");
+            },
+            Err(err) => eprintln!("Couldn't reload the data, so it is unchanged: {}", err),
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(path) = history_file() {
+            match load_history(&path) {
+                Ok(history) => self.history = history,
+                Err(err) => log::warn!("Couldn't read the history from {}: {}", path.display(), err),
+            }
+        }
+        while self.is_running() {
+            self.reload_if_changed();
+            // Mistakes shouldn't end the session, so only give up on errors that can't be fixed
+            // by trying again
+            match self.step() {
+                Ok(()) => {},
+                Err(err) if err.fatal => return Err(err.into()),
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Up to `count` entries from the history, most recent first, each only once.
+    fn recent_history(&self, count: usize) -> Vec<HistoryEntry> {
+        let mut entries: Vec<HistoryEntry> = Vec::new();
+        for entry in self.history.iter().rev() {
+            if entries.len() == count {
+                break;
+            }
+            if !entries.contains(entry) {
+                entries.push(entry.clone());
+            }
+        }
+        entries
+    }
+
+    /// Add `entry` to the history and the history file, unless it repeats the last entry.
+    fn record(&mut self, entry: HistoryEntry) {
+        if self.history.last() == Some(&entry) {
+            return;
+        }
+        if let Some(path) = history_file() {
+            let saved = fs::OpenOptions::new().create(true).append(true).open(&path)
+                .and_then(|mut file| writeln!(file, "{}", entry));
+            if let Err(err) = saved {
+                log::warn!("Couldn't save the history to {}: {}", path.display(), err);
+            }
+        }
+        self.history.push(entry);
+    }
+
+    /// Prompt for and carry out a single command in the current state.
+    fn step(&mut self) -> Result<(), CommandError> {
+        use AppState::*;
+        match &self.state {
+            Root => {
+                // Oldest first, so the most recent is just above the first command
+                let recent: Vec<RootChoice> = self.recent_history(RECENT_LENGTH).into_iter().rev().map(RootChoice::Recent).collect();
+                let first_command = recent.len();
+                let choices = recent.into_iter().chain(Command::iter().map(RootChoice::Command)).collect();
+                match Select::new("What would you like to do?", choices).with_starting_cursor(first_command).prompt()? {
+                    RootChoice::Recent(entry) => {
+                        entry.clone().run(self)?;
+                        self.record(entry);
+                    },
+                    RootChoice::Command(cmd) => {
+                        cmd.clone().run(self)?;
+                        if let Some(entry) = HistoryEntry::after(cmd, &self.state) {
+                            self.record(entry);
+                        }
+                    },
+                }
+            },
+            Event { id } => {
+                let Some(event) = self.event_map.get(id) else {
+                    return Err(CommandError::fatal("Select somehow returned an invalid event id."));
+                };
+                self.print_event(event)?;
+                let sub_cmd = Select::new("What would you like to do next?", EventSubCommand::iter().collect())
+                    .prompt()?;
+                sub_cmd.run(self)?;
+            },
+            NPC { id } => {
+                let Some(guid) = self.npc_guids.get_by_right(id) else {
+                    return Err(CommandError::fatal("Select somehow returned an invalid NPC Id."));
+                };
+                let Some(npc) = self.npc_map.get(guid) else {
+                    return Err(CommandError::fatal("NPC Id was mapped to an invalid NPC GUID."));
+                };
+                npc.print_details();
+                let sub_cmd = Select::new(&format!("What would you like to know about {}?", npc.id), NPCSubCommand::iter().collect())
+                    .prompt()?;
+                sub_cmd.run(&mut self.state, npc)?;
+            },
+            NPCEvents { npc_id } => {
+                let Some(event_ids) = self.npc_events.get(npc_id) else {
+                    return Err(CommandError::fatal("Somehow ended up with an invalid NPC Id."));
+                };
+                let mut list = format!("{} has the following events:\n", npc_id);
+                event_ids.iter().for_each(|e| {
+                    list.push_str(&format!("{}{}\n", indent(1), e));
+                });
+                show(&list);
+                let inspect = Confirm::new("Would you like to inspect one of these events?").prompt()?;
+                if inspect {
+                    let mut options = event_ids.clone();
+                    options.insert("cancel".to_owned());
+                    let event_id = Select::new("Which event would you like to inspect?", options.iter().collect())
+                        .prompt()?;
+                    if let Some(event) = self.event_map.get(event_id) {
+                        self.print_event(event)?;
+                    } else if event_id == "cancel" {
+                        println!("Cancelled.");
+                    } else {
+                        return Err("Select somehow returned an invalid event id.".into());
+                    };
+                }
+                self.state = NPC { id: npc_id.clone() };
+            },
+            Quit => { unreachable!("Loop should end as soon as we enter the AppState::Quit state"); }
+        }
+        Ok(())
+    }
+
+    /// Print the output requested on the command line, without any prompts.
+    pub fn run_batch(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.args.list {
+            self.list_events()?;
+        }
+        if self.args.count_only {
+            self.print_counts();
+        }
+        if self.args.event_npc_map {
+            for (id, event) in &self.event_map {
+                println!("{}\t{}", id, event.npc_id);
+            }
+        }
+        if self.args.list_npc_guids {
+            self.print_npc_guids();
+        }
+        if self.args.export_grouped {
+            self.print_grouped_events();
+        }
+        if self.args.inherited_decks {
+            self.print_inherited_decks()?;
+        }
+        if let Some(guid) = &self.args.npc_guid {
+            let npc = self.npc_by_guid(guid)?;
+            match self.args.format {
+                OutputFormat::Text => {
+                    npc.print_details();
+                    npc.print_all_decks()?;
+                },
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(npc)?),
+                OutputFormat::Yaml => print!("{}", serde_yaml::to_string(npc)?),
+            }
+        }
+        if let Some(predicate) = &self.args.npc_where {
+            let mut matching = 0;
+            for npc in self.npc_map.values().filter(|npc| predicate.matches(npc)) {
+                println!("{}: {}", npc.id, predicate.describe(npc));
+                matching += 1;
+            }
+            eprintln!("{} of {} NPC(s) match.", matching, self.npc_map.len());
+        }
+        if let Some(pattern) = &self.args.sequence {
+            let matching: Vec<&Event> = self.event_map.values()
+                .filter(|event| pattern.matches(&event.event.sequence_lengths))
+                .collect();
+            matching.iter().for_each(|event| println!("{}", event.compact()));
+            eprintln!("{} of {} event(s) match.", matching.len(), self.event_map.len());
+        }
+        if self.args.validate {
+            self.report_validation()?;
+        }
+        if let Some(path) = &self.args.diff {
+            self.print_event_diff(path)?;
+        }
+        if let Some(other) = &self.args.diff_folder {
+            self.print_folder_diff(other)?;
+        }
+        if self.args.check {
+            self.report_check()?;
+        }
+        Ok(())
+    }
+
+    /// Print a one line summary of what was loaded, or fail with the first thing that wasn't.
+    fn report_check(&mut self) -> Result<(), Box<dyn Error>> {
+        let count = self.load_failures.len();
+        match self.load_failures.drain(..).next() {
+            None => {
+                println!("OK: parsed {} events, {} NPCs", self.event_map.len(), self.npc_map.len());
+                Ok(())
+            },
+            Some((location, err)) => {
+                eprintln!("{} item(s) failed to load; the first was {}", count, location);
+                Err(err)
+            },
+        }
+    }
+
+    /// For each event that uses its NPC's deck, show which deck that is. The events don't record
+    /// which cycle they happen in, so the deck is only shown if `--cycle` was given.
+    fn print_inherited_decks(&self) -> Result<(), Box<dyn Error>> {
+        for event in self.event_map.values().filter(|event| event.event.deck.is_none()) {
+            let Some(npc) = self.npc_map.get(&event.event.npc_guid) else {
+                println!("{} ({}): no NPC has this guid, so the deck is unknown", event.event.id, event.npc_id);
+                continue;
+            };
+            match self.args.cycle {
+                Some(cycle) => {
+                    println!("{} ({}):", event.event.id, npc.id);
+                    npc.print_deck(cycle as usize)?;
+                },
+                None => println!("{} ({}): uses {}'s deck for the current cycle, 1 to {}", event.event.id, npc.id, npc.id, npc.cycle_count()),
+            }
+        }
+        if self.args.cycle.is_none() {
+            eprintln!("Pass --cycle to show the decks for a particular cycle.");
+        }
+        Ok(())
+    }
+
+    fn print_counts(&self) {
+        let override_cards = print_event_counts(self.event_map.values().map(|event| &event.event));
+        let npc_cards: usize = self.npc_map.values()
+            .flat_map(|npc| npc.decks.iter())
+            .map(Deck::card_count)
+            .sum();
+        println!("NPCs: {}", self.npc_map.len());
+        println!("Cards in NPC decks: {}", npc_cards);
+        println!("Cards in all decks: {}", override_cards + npc_cards);
+    }
+
+    fn list_events(&self) -> Result<(), Box<dyn Error>> {
+        let (listed, skipped): (Vec<&Event>, Vec<&Event>) = self.event_map.values()
+            .partition(|event| !self.args.overrides_only || event.event.deck.is_some());
+        if self.args.fields.is_some() && self.args.format == OutputFormat::Text {
+            return Err("--fields only works with --format json or yaml.".into());
+        }
+        match self.args.format {
+            OutputFormat::Text => {
+                for event in listed {
+                    if self.args.compact {
+                        println!("{}", event.compact());
+                    } else {
+                        println!("Event - {}", event);
+                    }
+                }
+            },
+            OutputFormat::Json => {
+                match &self.args.fields {
+                    Some(fields) => println!("{}", serde_json::to_string_pretty(&project_events(&listed, fields)?)?),
+                    None => {
+                        let events: Vec<&RawEvent> = listed.iter().map(|event| &event.event).collect();
+                        println!("{}", serde_json::to_string_pretty(&events)?);
+                    },
+                }
+            },
+            OutputFormat::Yaml => {
+                match &self.args.fields {
+                    Some(fields) => print!("{}", serde_yaml::to_string(&project_events(&listed, fields)?)?),
+                    None => {
+                        let events: Vec<&RawEvent> = listed.iter().map(|event| &event.event).collect();
+                        print!("{}", serde_yaml::to_string(&events)?);
+                    },
+                }
+            },
+        }
+        if !skipped.is_empty() {
+            eprintln!("Left out {} event(s) that use their NPC's deck.", skipped.len());
+        }
+        Ok(())
+    }
+
+    /// Compare the loaded events against events exported as JSON, treating the export as the older
+    /// version.
+    fn print_event_diff(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let imported = events_from_json(&fs::read_to_string(path)?)?;
+        let older: BTreeMap<String, &RawEvent> = imported.iter().map(|event| (event.id.clone(), event)).collect();
+        let newer: BTreeMap<String, &RawEvent> = self.event_map.iter().map(|(id, event)| (id.clone(), &event.event)).collect();
+        println!("Compared to `{}`:", path.display());
+        print_changes("Events", &newer, &older, RawEvent::describe_changes);
+        Ok(())
+    }
+
+    /// Compare the loaded events and NPCs against the ones in another folder, treating that folder
+    /// as the older version.
+    fn print_folder_diff(&self, other: &Path) -> Result<(), Box<dyn Error>> {
+        let older = App::new(self.args.clone(), other)?;
+        fn events(app: &App) -> BTreeMap<String, &RawEvent> {
+            app.event_map.iter().map(|(id, event)| (id.clone(), &event.event)).collect()
+        }
+        fn npcs(app: &App) -> BTreeMap<String, &NPC> {
+            app.npc_map.values().map(|npc| (npc.id.clone(), npc)).collect()
+        }
+        println!("Compared to `{}`:", other.display());
+        print_changes("Events", &events(self), &events(&older), RawEvent::describe_changes);
+        print_changes("NPCs", &npcs(self), &npcs(&older), NPC::describe_changes);
+        Ok(())
+    }
+
+    /// Print every group of 2 or more events that override their NPC's deck with identical decks,
+    /// largest groups first.
+    fn print_duplicate_decks(&self) {
+        let mut groups: BTreeMap<&Deck, Vec<&str>> = BTreeMap::new();
+        for (id, event) in &self.event_map {
+            if let Some(deck) = &event.event.deck {
+                groups.entry(deck).or_default().push(id);
+            }
+        }
+
+        let mut clusters: Vec<_> = groups.into_iter()
+            .filter(|(_, ids)| ids.len() >= 2)
+            .collect();
+        clusters.sort_by_key(|(_, ids)| Reverse(ids.len()));
+
+        if clusters.is_empty() {
+            println!("No events share an override deck.");
+        }
+        for (deck, ids) in clusters {
+            println!("{} events share this deck:", ids.len());
+            println!("{}{}", indent(1), ids.join(", "));
+            println!("{}", deck);
+        }
+    }
+
+    /// Find an NPC by its guid, suggesting the closest known guids if there isn't one.
+    fn npc_by_guid(&self, guid: &str) -> Result<&NPC, CommandError> {
+        if let Some(npc) = self.npc_map.get(guid) {
+            return Ok(npc);
+        }
+        let mut closest: Vec<(&String, &NPC)> = self.npc_map.iter().collect();
+        closest.sort_by_key(|(known, _)| edit_distance(guid, known));
+        let suggestions: Vec<String> = closest.iter()
+            .take(3)
+            .map(|(known, npc)| format!("{} ({})", known, npc.id))
+            .collect();
+        Err(if suggestions.is_empty() {
+            format!("No NPC has guid `{}`.", guid)
+        } else {
+            format!("No NPC has guid `{}`; the closest are {}.", guid, suggestions.join(", "))
+        }.into())
+    }
+
+    /// Every deck in the data, with a description of where it comes from.
+    fn deck_locations(&self) -> Vec<(String, &Deck)> {
+        let mut locations = Vec::new();
+        for npc in self.npc_map.values() {
+            for (cycle, deck) in npc.decks.iter().enumerate() {
+                locations.push((if cycle == 0 {
+                    format!("{}, fallback deck", npc.id)
                 } else {
-                    Err(format!("Unknown NPC Guid `{}` in event `{}`", raw.npc_guid, raw.id).into())
+                    format!("{}, cycle {} deck", npc.id, cycle)
+                }, deck));
+            }
+        }
+        for (id, event) in &self.event_map {
+            if let Some(deck) = &event.event.deck {
+                locations.push((format!("event {}, override deck", id), deck));
+            }
+        }
+        locations
+    }
+
+    fn print_card_query(&self, query: &CardQuery) {
+        let mut found = 0;
+        for (location, deck) in self.deck_locations() {
+            if let Some(anchor) = deck.anchor.as_ref().filter(|anchor| query.matches(anchor)) {
+                println!("{}{} (anchor): {}", indent(1), location, anchor);
+                found += 1;
+            }
+            for card in deck.cards.iter().filter(|card| query.matches(card)) {
+                println!("{}{}: {}", indent(1), location, card);
+                found += 1;
+            }
+        }
+        println!("Found {} matching card(s).", found);
+    }
+
+    /// Print every deck that contains exactly `target`, and how many copies it has.
+    fn print_card_trace(&self, target: &Card) {
+        let mut decks = 0;
+        let mut copies = 0;
+        for (location, deck) in self.deck_locations() {
+            let is_anchor = deck.anchor.as_ref() == Some(target);
+            let count = deck.cards.iter().filter(|card| *card == target).count();
+            if !is_anchor && count == 0 {
+                continue;
+            }
+            let mut line = format!("{}{}: ", indent(1), location);
+            if is_anchor {
+                line.push_str("anchor");
+                if count > 0 {
+                    line.push_str(", and ");
                 }
-            })
-            .collect::<Result<_, YamlError>>()?;
+            }
+            if count > 0 {
+                line.push_str(&format!("{} cop{}", count, if count == 1 { "y" } else { "ies" }));
+            }
+            println!("{}", line);
+            decks += 1;
+            copies += count + usize::from(is_anchor);
+        }
+        println!("`{}` appears {} time(s) in {} deck(s).", target, copies, decks);
+    }
+
+    /// Print an overview of the whole data set.
+    fn print_stats(&self) {
+        let mut deck_sizes = Vec::new();
+        let mut deck_weights = Vec::new();
+        let mut effects: BTreeMap<&Effect, usize> = BTreeMap::new();
+        let mut connectors: BTreeMap<&Connector, usize> = BTreeMap::new();
+        for (_, deck) in self.deck_locations() {
+            deck_sizes.push(deck.card_count());
+            deck_weights.push(deck.total_weight());
+            for card in deck.all_cards() {
+                if *card.effect() != Effect::None {
+                    *effects.entry(card.effect()).or_default() += 1;
+                }
+                *connectors.entry(card.input()).or_default() += 1;
+                *connectors.entry(card.output()).or_default() += 1;
+            }
+        }
+        let overrides = self.event_map.values().filter(|event| event.event.deck.is_some()).count();
+
+        println!("Events: {} ({} override their NPC's deck)", self.event_map.len(), overrides);
+        println!("NPCs: {}", self.npc_map.len());
+        if let (Some(min), Some(max)) = (deck_sizes.iter().min(), deck_sizes.iter().max()) {
+            let average = deck_sizes.iter().sum::<usize>() as f64 / deck_sizes.len() as f64;
+            println!("Deck size: {:.1} on average, from {} to {} ({} decks)", average, min, max, deck_sizes.len());
+        }
+        if let (Some(min), Some(max)) = (deck_weights.iter().min(), deck_weights.iter().max()) {
+            let average = deck_weights.iter().sum::<usize>() as f64 / deck_weights.len() as f64;
+            println!("Deck weight (connector types on all cards): {:.1} on average, from {} to {}", average, min, max);
+        }
+
+        let by_count = |counts: BTreeMap<String, usize>| {
+            let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+            counts.sort_by_key(|(_, count)| Reverse(*count));
+            counts
+        };
+        println!("Effects:");
+        for (effect, count) in by_count(effects.iter().map(|(effect, count)| (effect.name().to_owned(), *count)).collect()) {
+            println!("{}{:>5}  {}", indent(1), count, effect);
+        }
+        println!("Connectors:");
+        for (connector, count) in by_count(connectors.iter().map(|(connector, count)| (connector.to_string(), *count)).collect()) {
+            println!("{}{:>5}  {}", indent(1), count, if connector.is_empty() { "(none)" } else { &connector });
+        }
+    }
+
+    /// Print histograms of how many events have each number of concords and discords.
+    fn print_difficulty_distribution(&self) {
+        let mut concords: BTreeMap<u8, usize> = BTreeMap::new();
+        let mut discords: BTreeMap<u8, usize> = BTreeMap::new();
+        for event in self.event_map.values() {
+            *concords.entry(event.event.sequence_count).or_default() += 1;
+            *discords.entry(event.event.strike_count).or_default() += 1;
+        }
+        for (title, counts) in [("Concord", concords), ("Discord", discords)] {
+            println!("{} (events with each count):", title);
+            // Scale the bars down so the longest fits comfortably in a terminal
+            let longest = counts.values().copied().max().unwrap_or(0).max(HISTOGRAM_WIDTH);
+            for (value, count) in counts {
+                let bar = "#".repeat((count * HISTOGRAM_WIDTH).div_ceil(longest));
+                println!("{}{:>3} | {} {}", indent(1), value, bar, count);
+            }
+        }
+    }
+
+    /// Print the `count` events with the smallest and largest total sequence length.
+    fn print_event_extremes(&self, count: usize) {
+        let mut events: Vec<&Event> = self.event_map.values().collect();
+        // Stable, so events with the same total stay in id order
+        events.sort_by_key(|event| event.event.total_sequence_length());
+        let shortest: Vec<&Event> = events.iter().copied().take(count).collect();
+        let longest: Vec<&Event> = events.iter().copied().rev().take(count).collect();
+        for (title, extremes) in [("Shortest", shortest), ("Longest", longest)] {
+            println!("{} events (total sequence length):", title);
+            for event in extremes {
+                println!("{}{:>4}  {} ({})", indent(1), event.event.total_sequence_length(), event.event.id, event.npc_id);
+            }
+        }
+    }
+
+    /// Print every NPC ranked by the total weight of all its decks, the most complex first.
+    fn print_npc_complexity_ranking(&self) {
+        let mut ranking: Vec<(usize, &str)> = self.npc_map.values()
+            .map(|npc| (npc.decks.iter().map(Deck::total_weight).sum(), npc.id.as_str()))
+            .collect();
+        ranking.sort_by(|(a_weight, a_id), (b_weight, b_id)| b_weight.cmp(a_weight).then(a_id.cmp(b_id)));
+        println!("NPCs by total weight (connector types on all cards in all decks):");
+        for (rank, (weight, id)) in ranking.iter().enumerate() {
+            println!("{}{:>3}. {:>5}  {}", indent(1), rank + 1, weight, id);
+        }
+    }
+
+    /// Every issue found in the loaded data, errors first.
+    fn validation_issues(&self) -> Vec<ValidationIssue> {
+        let mut issues: Vec<ValidationIssue> = self.unresolved_events.iter()
+            .map(|(id, guid)| ValidationIssue::error(IssueCategory::Reference, id, &format!("refers to unknown NPC guid `{}`", guid)))
+            .collect();
+        issues.extend(self.duplicate_events.iter()
+            .map(|(id, first, duplicate)| ValidationIssue::error(IssueCategory::Duplicate, id, &format!("is in both {} and {}", first.display(), duplicate.display()))));
+        issues.extend(self.duplicate_assets.iter()
+            .map(|(shared, kept, skipped)| ValidationIssue::error(IssueCategory::Duplicate, &skipped.display().to_string(), &format!("has the same {} as {}", shared, kept.display()))));
+        issues.extend(self.event_map.values().flat_map(|event| event.event.validate()));
+        issues.extend(self.deck_locations().into_iter().flat_map(|(location, deck)| deck.effect_rule_issues(&location, &self.effect_rules)));
+        issues.sort_by_key(|issue| Reverse(issue.severity));
+        issues
+    }
+
+    /// Print the validation issues in the chosen `--format`, failing if any of them are errors.
+    fn report_validation(&self) -> Result<(), Box<dyn Error>> {
+        let issues = self.validation_issues();
+        match self.args.format {
+            OutputFormat::Text => self.print_validation(),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&issues)?),
+            OutputFormat::Yaml => print!("{}", serde_yaml::to_string(&issues)?),
+        }
+        let errors = issues.iter().filter(|issue| issue.severity == Severity::Error).count();
+        if errors > 0 {
+            return Err(ValidationError::from(format!("Validation found {} error(s).", errors)).into());
+        }
+        Ok(())
+    }
+
+    fn print_validation(&self) {
+        let issues = self.validation_issues();
+        if issues.is_empty() {
+            println!("No issues found.");
+        }
+        for issue in issues {
+            println!("{}{}", indent(1), issue);
+        }
+    }
+
+    /// Print `id<TAB>guid` for every NPC, sorted by id.
+    fn print_npc_guids(&self) {
+        for id in self.npc_guids.right_values() {
+            let guid = self.npc_guids.get_by_right(id).expect("id came from the map");
+            println!("{}\t{}", id, guid);
+        }
+    }
+
+    /// Print each NPC with its events beneath it, as a single document. Events whose NPC wasn't
+    /// found come last, under the guid they refer to.
+    fn print_grouped_events(&self) {
+        let print_events = |event_ids: Vec<&String>| {
+            for id in event_ids {
+                for line in format!("Event - {}", self.event_map[id]).lines() {
+                    println!("{}{}", if line.is_empty() { String::new() } else { indent(1) }, line);
+                }
+                println!();
+            }
+        };
+        for (npc_id, event_ids) in &self.npc_events {
+            println!("NPC - {} ({} event(s)):", npc_id, event_ids.len());
+            if event_ids.is_empty() {
+                println!();
+            }
+            print_events(event_ids.iter().collect());
+        }
+        let mut unresolved: BTreeMap<&String, Vec<&String>> = BTreeMap::new();
+        for (event_id, guid) in &self.unresolved_events {
+            unresolved.entry(guid).or_default().push(event_id);
+        }
+        for (guid, event_ids) in unresolved {
+            println!("Unknown NPC with guid {} ({} event(s)):", guid, event_ids.len());
+            print_events(event_ids);
+        }
+    }
+
+    /// Report events whose NPC guid doesn't match any NPC, and NPCs that no event uses.
+    fn print_reference_check(&self) {
+        if self.unresolved_events.is_empty() {
+            println!("Every event refers to a known NPC.");
+        } else {
+            println!("{} event(s) refer to an unknown NPC guid:", self.unresolved_events.len());
+            for (event_id, guid) in &self.unresolved_events {
+                println!("{}{} -> {}", indent(1), event_id, guid);
+            }
+        }
+
+        let unused: Vec<&str> = self.npc_events.iter()
+            .filter(|(_, events)| events.is_empty())
+            .map(|(npc_id, _)| npc_id.as_str())
+            .collect();
+        if unused.is_empty() {
+            println!("Every NPC is used by at least one event.");
+        } else {
+            println!("{} NPC(s) are unused:", unused.len());
+            println!("{}{}", indent(1), unused.join(&format!("\n{}", indent(1))));
+        }
+    }
+
+    fn select_event_id(&self) -> Result<String, CommandError> {
+        if self.args.type_to_search {
+            let completer = EventIdCompleter { ids: self.event_map.keys().cloned().collect() };
+            let validator = completer.clone();
+            let id = Text::new("Event id:")
+                .with_autocomplete(completer)
+                .with_validator(move |input: &str| Ok(validator.validate(input)))
+                .with_help_message("Type part of an id; tab to complete")
+                .prompt()?;
+            return Ok(id);
+        }
+        let id: &str = Select::new("Event id:", self.event_map.keys().collect())
+            .prompt()?;
+        Ok(id.to_owned())
+    }
+
+    fn print_event(&self, event: &Event) -> Result<(), CommandError> {
+        show(&format!("Event - {}\n", event));
+        // The NPC deck used depends on the cycle the player is in, which the event doesn't record,
+        // so it is only shown alongside the event when `--cycle` says which one
+        if let (None, Some(cycle)) = (&event.event.deck, self.args.cycle) {
+            if let Some(npc) = self.npc_map.get(&event.event.npc_guid) {
+                npc.print_deck(cycle as usize)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Print the deck an event that doesn't override its NPC's deck uses in a cycle.
+    fn print_inherited_deck(&self, event: &Event) -> Result<(), CommandError> {
+        if event.event.deck.is_some() {
+            println!("{} overrides its NPC's deck, so it always uses the deck shown above.", event.event.id);
+            return Ok(());
+        }
+        if self.args.only_events {
+            println!("NPC decks weren't loaded, because of --only-events.");
+            return Ok(());
+        }
+        let Some(npc) = self.npc_map.get(&event.event.npc_guid) else {
+            println!("No NPC has the guid `{}`, so the deck can't be shown.", event.event.npc_guid);
+            return Ok(());
+        };
+        npc.print_deck(self.cycle_for(npc)?)?;
+        Ok(())
+    }
+
+    /// The cycle given with `--cycle`, or else the one the user picks for `npc`.
+    fn cycle_for(&self, npc: &NPC) -> Result<usize, CommandError> {
+        match self.args.cycle {
+            Some(cycle) => Ok(cycle as usize),
+            None => select_cycle(&format!("Which cycle's deck would {} be using?", npc.id), npc),
+        }
+    }
+
+    /// Print a chain of cards for each part of the event's sequence, from the deck it overrides
+    /// its NPC's with, or otherwise the NPC's deck for a chosen cycle.
+    fn print_segment_plan(&self, event: &Event) -> Result<(), CommandError> {
+        let deck = match &event.event.deck {
+            Some(deck) => deck,
+            None => {
+                let Some(npc) = self.npc_map.get(&event.event.npc_guid) else {
+                    return Err(format!("No NPC has the guid `{}`, so there's no deck to plan with.", event.event.npc_guid).into());
+                };
+                npc.deck_for_cycle(Some(self.cycle_for(npc)?))?
+            },
+        };
+        for line in deck.segment_plan(&event.event.sequence_lengths) {
+            println!("{}{}", indent(1), line);
+        }
+        Ok(())
+    }
+
+    /// Load the events from `event_data.asset` in `folder_path`, or from the files given with
+    /// `--event-data`.
+    fn parse_event_data(&mut self, folder_path: &Path) -> Result<(), Box<dyn Error>> {
+        let files: Vec<PathBuf> = if self.args.event_data.is_empty() {
+            vec![folder_path.join("event_data.asset")]
+        } else {
+            self.args.event_data.iter().map(|file| folder_path.join(file)).collect()
+        };
+        let tag_sources = files.len() > 1;
+        for file in files {
+            self.parse_event_file(&file, tag_sources)?;
+        }
+        Ok(())
+    }
+
+    fn parse_event_file(&mut self, path: &Path, tag_source: bool) -> Result<(), Box<dyn Error>> {
+        let start = Instant::now();
+        let contents = fs::read_to_string(path)?;
+        let asset = UnityAsset::parse(&contents)?;
+        let class_id = asset.class_id;
+        let events = parse_asset_entries(asset)?;
+        let events = if self.args.skip_bad_events() {
+            events
+        } else {
+            events.into_iter().collect::<Result<Vec<_>, _>>()?.into_iter().map(Ok).collect()
+        };
+        log::info!("Parsed {} ({}) in {}ms", path.display(), format_size(contents.len() as u64), start.elapsed().as_millis());
+        log::info!("{}: Unity class {}", path.display(), describe_class_id(class_id));
+        warn_unexpected_class(path, class_id);
+
+        let mut failures = Vec::new();
+        for (index, event) in events.into_iter().enumerate() {
+            match event.map_err(Box::from).and_then(|raw| self.add_event(raw, path, tag_source)) {
+                Ok(()) => {},
+                Err(err) if self.args.skip_bad_events() => failures.push((index, err)),
+                Err(err) => return Err(err),
+            }
+        }
+
+        if !failures.is_empty() {
+            log::warn!("{} event(s) in {} failed to load; events with an unknown NPC are kept, the rest were skipped:", failures.len(), path.display());
+            for (index, err) in failures {
+                log::warn!("\tevent #{}: {}", index, err);
+                self.load_failures.push((format!("{}: event #{}", path.display(), index), err));
+            }
+        }
 
         Ok(())
     }
 
-    fn build_npc_maps(&mut self, folder_path: &PathBuf) -> Result<(), Box<dyn Error>> {
-        let meta_files =
+    /// Add an event loaded from `source`. If `tag_source` is set, the event will show which file
+    /// it came from.
+    fn add_event(&mut self, raw: RawEvent, source: &Path, tag_source: bool) -> Result<(), Box<dyn Error>> {
+        if let Some(first) = self.event_sources.get(&raw.id) {
+            log::warn!("Event `{}` in {} was already loaded from {}; keeping the first one.", raw.id, source.display(), first.display());
+            self.duplicate_events.push((raw.id.clone(), first.clone(), source.to_owned()));
+            return Ok(());
+        }
+
+        let mut unresolved = None;
+        let npc_id = if self.args.only_events {
+            // NPCs weren't loaded, so there's nothing to resolve the guid against
+            unknown_npc_id(&raw.npc_guid)
+        } else if let Some(npc_id) = self.npc_guids.get_by_left(&raw.npc_guid) {
+            // Insert to relevant npc_events set
+            let Some(event_set) = self.npc_events.get_mut(npc_id) else {
+                return Err(format!("NPC {} somehow wasn't added to the npc_events map", npc_id).into());
+            };
+            event_set.insert(raw.id.clone());
+            npc_id.clone()
+        } else {
+            // Still added, so that it can be looked at if the error is skipped
+            self.unresolved_events.insert(raw.id.clone(), raw.npc_guid.clone());
+            unresolved = Some(ValidationError::from(format!("Unknown NPC Guid `{}` in event `{}`", raw.npc_guid, raw.id)));
+            unknown_npc_id(&raw.npc_guid)
+        };
+
+        for issue in raw.validate() {
+            log::warn!("event {}: {}", issue.entity_id, issue.message);
+        }
+
+        // Create the actual Event struct for the event_map
+        let mad_threshold = self.npc_map.get(&raw.npc_guid).map(|npc| npc.mad_threshold);
+        self.event_sources.insert(raw.id.clone(), source.to_owned());
+        self.event_map.insert(raw.id.clone(), Event {
+            npc_id,
+            event: raw,
+            source: tag_source.then(|| source.to_owned()),
+            mad_threshold,
+        });
+
+        match unresolved {
+            Some(err) if self.args.keep_unresolved_events() => {
+                log::warn!("{}", err);
+                Ok(())
+            },
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+
+    fn build_npc_maps(&mut self, folder_path: &Path) -> Result<(), Box<dyn Error>> {
+        let start = Instant::now();
+        let mut meta_paths: Vec<PathBuf> =
             WalkDir::new(folder_path)
             .min_depth(1)
+            .follow_links(self.args.follow_symlinks)
             .into_iter()
-            .filter_entry(|entry| {
-                entry.file_name().to_str().map_or(false, |name| name.ends_with(".meta"))
+            .filter_map(|entry| match entry {
+                Ok(entry) => Some(entry),
+                Err(err) => {
+                    if let Some(ancestor) = err.loop_ancestor() {
+                        log::warn!("Skipping {}: it links back to {}", err.path().unwrap_or(ancestor).display(), ancestor.display());
+                    }
+                    None // Silently skip permission errors
+                },
             })
-            .flatten(); // Silently skip permission errors
+            .filter(|entry| {
+                !entry.file_type().is_dir() && entry.file_name().to_str().is_some_and(|name| name.ends_with(".meta"))
+            })
+            .map(walkdir::DirEntry::into_path)
+            // Folders have `.meta` files too, but aren't assets
+            .filter(|meta_path| !meta_path.with_extension("").is_dir())
+            .collect();
+        // Files are parsed in parallel, but the results are handled in path order so that the
+        // same error is reported every time
+        meta_paths.sort();
 
-        for meta_file in meta_files {
-            let meta_path = meta_file.into_path();
-            let asset_path = meta_path.with_extension("");
+        // Find every asset's guid first, so that NPCs can refer to decks in other assets
+        let mut skipped = 0;
+        let mut assets: BTreeMap<String, PathBuf> = BTreeMap::new(); // (Guid, asset path)
+        let guids: Vec<_> = meta_paths.par_iter().map(|meta_path| Self::read_meta_guid(meta_path)).collect();
+        for (meta_path, guid) in meta_paths.iter().zip(guids) {
+            match guid {
+                Ok(guid) => {
+                    let asset_path = meta_path.with_extension("");
+                    // Inserting would silently drop one of them
+                    match assets.get(&guid) {
+                        Some(other) => self.duplicate_asset(format!("guid `{}`", guid), other, &asset_path)?,
+                        None => { assets.insert(guid, asset_path); },
+                    }
+                },
+                Err(err) if self.args.skip_bad_npcs() => {
+                    log::warn!("Skipping {}: {}", meta_path.display(), err);
+                    self.load_failures.push((meta_path.display().to_string(), err));
+                    skipped += 1;
+                },
+                Err(err) => return Err(err),
+            }
+        }
 
-            let meta_yaml: Field = serde_yaml::from_reader(File::open(meta_path)?)?;
-            let Field::Struct(meta_map) = meta_yaml else { return Err("Root isn't a map".into()); };
-            let ref_meta_map = &meta_map;
-            field_get!(let guid: Str = ref_meta_map.guid);
+        let resolve = |guid: &str| -> Result<Field, Box<dyn Error + Send + Sync>> {
+            let Some(path) = assets.get(guid) else {
+                return Err("no asset with that guid was found.".into());
+            };
+            Ok(Field::Struct(UnityAsset::parse(&fs::read_to_string(path)?)?.into_monobehaviour()?))
+        };
 
-            if let Some(npc) = NPC::load_asset(asset_path)? {
-                self.npc_guids.insert(guid.clone(), npc.id.clone());
-                self.npc_events.insert(npc.id.clone(), BTreeSet::new());
-                self.npc_map.insert(guid.clone(), npc);
+        let mut asset_paths: Vec<(&String, &PathBuf)> = assets.iter().collect();
+        asset_paths.sort_by_key(|(_, asset_path)| *asset_path);
+        let npcs: Vec<_> = asset_paths.par_iter().map(|(_, asset_path)| NPC::load_asset(asset_path, &resolve)).collect();
+        for ((guid, asset_path), npc) in asset_paths.into_iter().zip(npcs) {
+            match npc {
+                Ok(Some(npc)) => {
+                    if let Some(other) = self.npc_guids.get_by_right(&npc.id).cloned() {
+                        self.duplicate_asset(format!("NPC id `{}`", npc.id), &assets[&other], asset_path)?;
+                        continue;
+                    }
+                    log::debug!("{}: NPC {}, Unity class {}", asset_path.display(), npc.id, describe_class_id(npc.class_id));
+                    warn_unexpected_class(asset_path, npc.class_id);
+                    self.npc_guids.insert(guid.clone(), npc.id.clone());
+                    self.npc_events.insert(npc.id.clone(), BTreeSet::new());
+                    self.npc_map.insert(guid.clone(), npc);
+                },
+                Ok(None) => {},
+                Err(err) if self.args.skip_bad_npcs() => {
+                    log::warn!("Skipping {}: {}", asset_path.display(), err);
+                    self.load_failures.push((asset_path.display().to_string(), err));
+                    skipped += 1;
+                },
+                Err(err) => return Err(err),
             }
         }
 
+        if skipped > 0 {
+            log::warn!("Skipped {} NPC asset(s) that failed to load.", skipped);
+        }
+        log::info!("Loaded {} NPC(s) from {} asset(s) in {}ms", self.npc_map.len(), meta_paths.len(), start.elapsed().as_millis());
+
         Ok(())
     }
+
+    /// Handle an asset at `duplicate` that has the same guid or NPC id (`shared`) as the one at
+    /// `kept`. The first asset is kept; loading only stops if neither `--skip-bad-npcs` nor
+    /// `--validate` is given.
+    fn duplicate_asset(&mut self, shared: String, kept: &Path, duplicate: &Path) -> Result<(), Box<dyn Error>> {
+        let message = format!("{} and {} both have the {}", kept.display(), duplicate.display(), shared);
+        if self.args.skip_bad_npcs() {
+            log::warn!("Skipping {}: {}", duplicate.display(), message);
+            self.load_failures.push((duplicate.display().to_string(), ValidationError::from(message).into()));
+        } else if self.args.validate {
+            log::warn!("{}; keeping the first one.", message);
+            self.duplicate_assets.push((shared, kept.to_owned(), duplicate.to_owned()));
+        } else {
+            return Err(ValidationError::from(message).into());
+        }
+        Ok(())
+    }
+
+    /// Read the guid of the asset described by the `.meta` file at `meta_path`.
+    fn read_meta_guid(meta_path: &Path) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let meta_yaml: Field = serde_yaml::from_reader(File::open(meta_path)?)?;
+        let Some(guid) = meta_yaml.get("guid").and_then(Field::as_str) else {
+            return Err(YamlError::MissingField { entity: None, key: "guid".to_owned() }.into());
+        };
+        Ok(guid.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use clap::Parser;
+
+    use super::*;
+    use crate::fixtures;
+
+    /// The app for `path`, loaded with the flags in `args`.
+    fn app(path: &Path, args: &[&str]) -> Result<App, Box<dyn Error>> {
+        let args = Args::try_parse_from([OsStr::new("sots-event-inspect"), path.as_os_str()].into_iter().chain(args.iter().map(OsStr::new)))?;
+        App::new(args, path)
+    }
+
+    /// A folder like `fixtures::asset_folder`, but with `ev_intro` referring to an NPC that isn't there.
+    fn unknown_guid_folder(name: &str) -> PathBuf {
+        let events = fixtures::EVENT_DATA.replacen(fixtures::NPC_GUID, "ffff0000", 1);
+        fixtures::folder(name, &[
+            ("event_data.asset", &events),
+            ("alice.asset", fixtures::NPC_ASSET),
+            ("alice.asset.meta", fixtures::NPC_META),
+        ])
+    }
+
+    #[test]
+    fn interactive_keeps_unknown_guid_for_reference_check() {
+        let app = app(&unknown_guid_folder("interactive_keeps_unknown_guid_for_reference_check"), &[]).unwrap();
+        assert_eq!(app.unresolved_events.get("ev_intro").map(String::as_str), Some("ffff0000"));
+        assert!(app.npc_events["alice"].contains("ev_custom"));
+    }
+
+    #[test]
+    fn single_cycle_isnt_asked_for() {
+        let fallback_only = fixtures::npc_in(fixtures::NPC_ASSET.split("  deck1:").next().unwrap());
+        assert_eq!(select_cycle("", &fallback_only).unwrap(), 0);
+        assert!(fallback_only.deck_for_cycle(Some(0)).is_ok());
+
+        let one_cycle = fixtures::npc_in(fixtures::NPC_ASSET.split("  deck2:").next().unwrap());
+        assert_eq!(select_cycle("", &one_cycle).unwrap(), 1);
+    }
+
+    #[test]
+    fn event_display_matches_golden() {
+        let app = app(&fixtures::asset_folder("event_display_matches_golden"), &[]).unwrap();
+        fixtures::assert_golden("event_override_deck", &app.event_map["ev_custom"].to_string());
+        fixtures::assert_golden("event_npc_deck", &app.event_map["ev_intro"].to_string());
+    }
+
+    #[test]
+    fn validate_reports_unknown_guid() {
+        let app = app(&unknown_guid_folder("validate_reports_unknown_guid"), &["--validate", "--format", "json"]).unwrap();
+        let issues = serde_json::to_value(app.validation_issues()).unwrap();
+        assert!(issues.as_array().unwrap().contains(&serde_json::json!({
+            "severity": "error",
+            "category": "reference",
+            "entity_id": "ev_intro",
+            "message": "refers to unknown NPC guid `ffff0000`",
+        })), "{}", issues);
+        assert!(app.event_map.contains_key("ev_custom"));
+    }
+
+    /// Compares loading a large folder of NPCs on one thread with loading it on every core.
+    #[test]
+    #[ignore = "timing only; run with `cargo test --release -- --ignored --nocapture npc_loading_timing`"]
+    fn npc_loading_timing() {
+        let files: Vec<(String, String)> = (0..3000)
+            .flat_map(|n| [
+                (format!("npc{}.asset", n), fixtures::NPC_ASSET.replace("id: alice", &format!("id: npc{}", n))),
+                (format!("npc{}.asset.meta", n), fixtures::NPC_META.replace(fixtures::NPC_GUID, &format!("c{:07x}", n))),
+            ])
+            .collect();
+        let files: Vec<(&str, &str)> = files.iter().map(|(file, contents)| (file.as_str(), contents.as_str())).collect();
+        let path = fixtures::folder("npc_loading_timing", &files);
+
+        let mut thread_counts = vec![1, std::thread::available_parallelism().map_or(1, usize::from)];
+        thread_counts.dedup();
+        for threads in thread_counts {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            let start = Instant::now();
+            let loaded = pool.install(|| app(&path, &["--only-npc-decks"]).map(|app| app.npc_map.len()).map_err(|err| err.to_string()));
+            println!("{} thread(s): loaded {} NPCs in {}ms", threads, loaded.unwrap(), start.elapsed().as_millis());
+        }
+    }
+
+    #[test]
+    fn recent_history_is_newest_first_without_repeats() {
+        let mut app = app(&fixtures::asset_folder("recent_history_is_newest_first_without_repeats"), &[]).unwrap();
+        app.history = ["view event ev_intro", "stats", "view npc alice", "view event ev_intro", "legend"].iter()
+            .map(|entry| entry.parse().unwrap())
+            .collect();
+        let recent: Vec<String> = app.recent_history(3).iter().map(HistoryEntry::to_string).collect();
+        assert_eq!(recent, ["legend", "view event ev_intro", "view npc alice"]);
+        assert_eq!(app.recent_history(RECENT_LENGTH).len(), 4);
+    }
+
+    #[test]
+    fn shared_guid_names_both_assets() {
+        let path = fixtures::folder("shared_guid_names_both_assets", &[
+            ("event_data.asset", fixtures::EVENT_DATA),
+            ("alice.asset", fixtures::NPC_ASSET),
+            ("alice.asset.meta", fixtures::NPC_META),
+            ("alice_copy.asset", &fixtures::NPC_ASSET.replace("id: alice", "id: alice_copy")),
+            ("alice_copy.asset.meta", fixtures::NPC_META),
+        ]);
+        let Err(err) = app(&path, &[]) else { panic!("shared guid should fail to load"); };
+        assert!(err.is::<ValidationError>());
+        assert_eq!(err.to_string(), format!("{} and {} both have the guid `{}`",
+            path.join("alice.asset").display(), path.join("alice_copy.asset").display(), fixtures::NPC_GUID));
+    }
+
+    #[test]
+    fn shared_npc_id_names_both_assets() {
+        let path = fixtures::folder("shared_npc_id_names_both_assets", &[
+            ("event_data.asset", fixtures::EVENT_DATA),
+            ("alice.asset", fixtures::NPC_ASSET),
+            ("alice.asset.meta", fixtures::NPC_META),
+            ("alice_copy.asset", fixtures::NPC_ASSET),
+            ("alice_copy.asset.meta", &fixtures::NPC_META.replace(fixtures::NPC_GUID, "bbbb2222")),
+        ]);
+        let Err(err) = app(&path, &[]) else { panic!("shared id should fail to load"); };
+        assert!(err.is::<ValidationError>());
+        assert_eq!(err.to_string(), format!("{} and {} both have the NPC id `alice`",
+            path.join("alice.asset").display(), path.join("alice_copy.asset").display()));
+    }
+
+    #[test]
+    fn shared_guid_is_skipped_with_skip_bad_npcs() {
+        let path = fixtures::folder("shared_guid_is_skipped_with_skip_bad_npcs", &[
+            ("event_data.asset", fixtures::EVENT_DATA),
+            ("alice.asset", fixtures::NPC_ASSET),
+            ("alice.asset.meta", fixtures::NPC_META),
+            ("alice_copy.asset", &fixtures::NPC_ASSET.replace("id: alice", "id: alice_copy")),
+            ("alice_copy.asset.meta", fixtures::NPC_META),
+        ]);
+        let app = app(&path, &["--skip-bad-npcs"]).unwrap();
+        assert_eq!(app.npc_guids.get_by_left(fixtures::NPC_GUID).map(String::as_str), Some("alice"));
+        let failures: Vec<&str> = app.load_failures.iter().map(|(location, _)| location.as_str()).collect();
+        assert_eq!(failures, [path.join("alice_copy.asset").display().to_string()]);
+    }
+
+    #[test]
+    fn shared_npc_id_is_a_validation_issue() {
+        let path = fixtures::folder("shared_npc_id_is_a_validation_issue", &[
+            ("event_data.asset", fixtures::EVENT_DATA),
+            ("alice.asset", fixtures::NPC_ASSET),
+            ("alice.asset.meta", fixtures::NPC_META),
+            ("alice_copy.asset", fixtures::NPC_ASSET),
+            ("alice_copy.asset.meta", &fixtures::NPC_META.replace(fixtures::NPC_GUID, "bbbb2222")),
+        ]);
+        let app = app(&path, &["--validate"]).unwrap();
+        assert!(app.validation_issues().iter().any(|issue| issue.category == IssueCategory::Duplicate
+            && issue.entity_id == path.join("alice_copy.asset").display().to_string()
+            && issue.message.contains("NPC id `alice`")));
+    }
+
+    #[test]
+    fn batch_listing_fails_on_unknown_guid() {
+        assert!(app(&unknown_guid_folder("batch_listing_fails_on_unknown_guid"), &["--list"]).is_err());
+    }
 }
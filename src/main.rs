@@ -1,35 +1,360 @@
 use std::{
-    error::Error,
-    path::PathBuf,
+    path::{ Path, PathBuf },
+    process::exit,
 };
-use clap::Parser;
+use clap::{ ArgAction, ArgGroup, Parser };
+use log::LevelFilter;
 
 mod data;
+mod error;
 mod yaml;
 mod interface;
+mod settings;
+mod watch;
+#[cfg(test)]
+mod fixtures;
+use data::{ ConnectType, ConnectorOrder, EventFields, NPCPredicate, SequencePattern };
+use error::Failure;
 use interface::*;
+use settings::{ ColorChoice, ConnectorStyle, IndentStyle, NoEffectLabel, OutputFormat, PagerChoice, Settings };
+use watch::AssetWatcher;
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
+#[command(group(ArgGroup::new("formatted").args(["list", "validate", "npc_guid"]).multiple(true)))]
 pub struct Args {
-    /// Path to the folder containing all the `.asset` files
-    path: PathBuf,
+    /// Path to the folder containing all the `.asset` files. Can be given more than once, and
+    /// single `.asset` files can be given too, e.g. from `find . -name '*.asset' | xargs`.
+    /// Defaults to $SOTS_ASSET_PATH if no path is given.
+    #[arg(required_unless_present = "load_cache", value_name = "PATH", env = "SOTS_ASSET_PATH")]
+    paths: Vec<PathBuf>,
+
+    /// Print every event and exit, instead of starting the interactive session
+    #[arg(long)]
+    list: bool,
+
+    /// Only list events that override their NPC's deck
+    #[arg(long, requires = "list")]
+    overrides_only: bool,
+
+    /// How to print the output of `--list`, `--validate` or `--npc-guid`
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, requires = "formatted")]
+    format: OutputFormat,
+
+    /// Only include these fields of each event in `--list --format json` or `yaml`, e.g.
+    /// `id,npc_id,sequence_count`
+    #[arg(long, value_name = "FIELDS", requires = "list")]
+    fields: Option<EventFields>,
+
+    /// Print each listed event on a single line: `id | npc | C<concord>/D<discord> | lengths | override`
+    #[arg(long, requires = "list")]
+    compact: bool,
+
+    /// Print `event_id<TAB>npc_id` for every event, sorted by event id, and exit
+    #[arg(long)]
+    event_npc_map: bool,
+
+    /// Print `npc_id<TAB>guid` for every NPC, sorted by id, and exit
+    #[arg(long)]
+    list_npc_guids: bool,
+
+    /// Print every NPC, sorted by id, with the details of each of its events beneath it, and exit
+    #[arg(long, conflicts_with = "only_events")]
+    export_grouped: bool,
+
+    /// Print every event that doesn't override its NPC's deck with the NPC deck it uses instead,
+    /// for the cycle given with `--cycle`, and exit
+    #[arg(long, conflicts_with = "only_events")]
+    inherited_decks: bool,
+
+    /// Print the details and decks of the NPC with this guid, and exit
+    #[arg(long, value_name = "GUID")]
+    npc_guid: Option<String>,
+
+    /// Print the NPCs matching a condition such as `hand_size >= 10` or `prefers_doubles == true`,
+    /// and exit. Fields are `hand_size`, `mad_threshold` and `prefers_doubles`.
+    #[arg(long, value_name = "PREDICATE")]
+    npc_where: Option<NPCPredicate>,
+
+    /// Print the events whose sequence lengths match a pattern, and exit. The pattern is a comma
+    /// separated list of lengths, where `*` matches any length and a trailing `...` allows more,
+    /// e.g. `3,4,5` or `3,*,...`.
+    #[arg(long, value_name = "PATTERN")]
+    sequence: Option<SequencePattern>,
+
+    /// Check the data for problems, print them, and exit. Exits with an error if any are errors
+    /// rather than warnings.
+    #[arg(long)]
+    validate: bool,
+
+    /// Check cards against the effect rules in FILE, a yaml or json list, instead of the default
+    /// ones. See the README for the format.
+    #[arg(long, value_name = "FILE")]
+    effect_rules: Option<PathBuf>,
+
+    /// Parse every event and NPC, print how many there were or the first error, and exit.
+    /// Exits with an error if anything failed to load.
+    #[arg(long)]
+    check: bool,
+
+    /// With `--check`, stop at the first error instead of parsing everything else first
+    #[arg(long, requires = "check")]
+    strict: bool,
+
+    /// Compare the events in PATH against ones saved with `--list --format json`,
+    /// printing which were added, removed or changed
+    #[arg(long, value_name = "FILE.json")]
+    diff: Option<PathBuf>,
+
+    /// Compare PATH against another asset folder, such as one from an older version of the game,
+    /// printing the events and NPCs that were added, removed or changed
+    #[arg(long, value_name = "OTHER")]
+    diff_folder: Option<PathBuf>,
+
+    /// Print how many events, NPCs and cards there are and exit.
+    /// For a single `.asset` file, only what is in that file is counted.
+    #[arg(long)]
+    count_only: bool,
+
+    /// Choose events by typing their id, with auto-completion, instead of scrolling a list
+    #[arg(long)]
+    type_to_search: bool,
+
+    /// Keep going when individual events fail to parse, and report the failures at the end
+    #[arg(long)]
+    best_effort: bool,
+
+    /// Event data file to load instead of `event_data.asset`, relative to PATH.
+    /// Can be given more than once to merge events from several files.
+    #[arg(long, value_name = "FILE")]
+    event_data: Vec<PathBuf>,
+
+    /// Only load NPC decks, without reading `event_data.asset`
+    #[arg(long, conflicts_with = "only_events")]
+    only_npc_decks: bool,
+
+    /// Only load `event_data.asset`, without looking for NPC assets.
+    /// Events will show their NPC's guid instead of its id.
+    #[arg(long)]
+    only_events: bool,
+
+    /// Skip NPC assets that can't be loaded, instead of stopping
+    #[arg(long)]
+    skip_bad_npcs: bool,
+
+    /// Follow symlinks when looking for NPC assets in PATH and its subfolders. Off by default;
+    /// symlinks that lead back into a folder already being searched are skipped with a warning.
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Cycle to assume when showing the NPC deck used by events that don't override it.
+    /// If omitted, you will be asked each time.
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..))]
+    cycle: Option<u8>,
+
+    /// Fail when part of an event's `sequence` can't be parsed, rather than skipping it
+    #[arg(long)]
+    strict_sequence: bool,
+
+    /// Also show each event's `sequence` and `overrideDeck` fields as they appear in the asset
+    #[arg(long)]
+    show_raw_fields: bool,
+
+    /// Don't print the note on events that use their NPC's deck, saying to look at the NPC
+    #[arg(long)]
+    hide_inheritance_note: bool,
+
+    /// Don't print notes explaining the data, such as the inheritance note and when an event can
+    /// make its NPC mad
+    #[arg(long)]
+    hide_notes: bool,
+
+    /// Show identical cards in a deck once, with a count, instead of listing each copy
+    #[arg(long)]
+    group_cards: bool,
+
+    /// Treat this connector type as a wildcard that matches anything when working out which
+    /// cards can be played, e.g. `@`
+    #[arg(long, value_name = "SYMBOL")]
+    wildcard: Option<ConnectType>,
+
+    /// The order to show the symbols within a connector, e.g. `@○△□◊🐾` or `PCTSD`, to match the game.
+    /// Symbols left out are shown last. Defaults to ○△□◊🐾@.
+    #[arg(long, value_name = "SYMBOLS")]
+    connector_order: Option<ConnectorOrder>,
+
+    /// How to draw connector types. Defaults to `unicode`, or `letters` if built with the
+    /// `display_compat` feature.
+    #[arg(long, value_enum, value_name = "STYLE")]
+    connector_style: Option<ConnectorStyle>,
+
+    /// How to show that a card has no effect. By default it's left out, which keeps decks
+    /// easy to read, but can leave blanks where an effect is expected.
+    #[arg(long, value_enum, value_name = "LABEL", default_value_t = NoEffectLabel::Blank)]
+    no_effect_label: NoEffectLabel,
+
+    /// Whether to mark card effects with coloured symbols
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Whether to show long interactive output, such as all of an NPC's decks, through a pager.
+    /// Uses $PAGER, or `less -R` if it isn't set.
+    #[arg(long, value_enum, default_value_t = PagerChoice::Auto)]
+    pager: PagerChoice,
+
+    /// When showing the source file of an event or NPC, also open it in $EDITOR
+    #[arg(long)]
+    editor: bool,
+
+    /// How to indent nested lines, such as event details and deck cards
+    #[arg(long, value_enum, default_value_t = IndentStyle::Tabs)]
+    indent: IndentStyle,
+
+    /// After loading PATH, save everything that was loaded to FILE, so that later runs can use
+    /// `--load-cache` instead of parsing the assets again
+    #[arg(long, value_name = "FILE")]
+    save_cache: Option<PathBuf>,
+
+    /// Load the data saved by `--save-cache` from FILE, instead of reading PATH
+    #[arg(long, value_name = "FILE", conflicts_with = "save_cache")]
+    load_cache: Option<PathBuf>,
+
+    /// Keep running, and load PATH again whenever its assets change. Output that was asked for on
+    /// the command line is printed again, and the interactive session carries on with the new data.
+    #[arg(long, conflicts_with = "load_cache")]
+    watch: bool,
+
+    /// Print more diagnostic output; repeat for even more (-v, -vv, -vvv)
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+}
+
+impl Args {
+    /// Whether to print the requested output and exit, rather than starting the interactive session.
+    fn is_batch(&self) -> bool {
+        self.list || self.count_only || self.event_npc_map || self.list_npc_guids || self.export_grouped || self.inherited_decks || self.npc_guid.is_some() || self.npc_where.is_some() || self.sequence.is_some() || self.validate || self.check || self.diff.is_some() || self.diff_folder.is_some() || self.save_cache.is_some()
+    }
+
+    /// Whether to keep going when an event fails to parse.
+    fn skip_bad_events(&self) -> bool {
+        self.best_effort || (self.check && !self.strict)
+    }
+
+    /// Whether to keep going when an NPC asset fails to load.
+    fn skip_bad_npcs(&self) -> bool {
+        self.skip_bad_npcs || (self.check && !self.strict)
+    }
+
+    /// Whether to keep loading past events whose NPC guid isn't known, so that they can be
+    /// reported by the reference check or `--validate` rather than stopping the load.
+    fn keep_unresolved_events(&self) -> bool {
+        self.validate || !self.is_batch()
+    }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() {
     let args = Args::parse();
 
-    if args.path.is_dir() {
-        let mut app = App::new(args)?;
+    env_logger::Builder::new()
+        .filter_level(match args.verbose {
+            // `--check` reports what failed itself
+            0 if args.check => LevelFilter::Error,
+            0 => LevelFilter::Warn,
+            1 => LevelFilter::Info,
+            2 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        })
+        .parse_default_env()
+        .init();
+
+    let terminal_size = settings::detect_terminal_size();
+    settings::init(Settings {
+        strict_sequence: args.strict_sequence,
+        group_cards: args.group_cards,
+        color: args.color.enabled(),
+        wildcard: args.wildcard.clone(),
+        connector_order: args.connector_order.clone(),
+        terminal_width: terminal_size.map(|(width, _)| width),
+        terminal_height: terminal_size.map(|(_, height)| height),
+        pager: args.pager,
+        open_in_editor: args.editor,
+        show_raw_fields: args.show_raw_fields,
+        show_notes: !args.hide_notes,
+        show_inheritance_note: !(args.hide_notes || args.hide_inheritance_note),
+        indent: args.indent,
+        connector_style: args.connector_style.unwrap_or_default(),
+        no_effect_label: args.no_effect_label,
+    });
+
+    if let Err(failure) = run(args) {
+        eprintln!("Error: {}", failure);
+        exit(failure.exit_code());
+    }
+}
+
+fn run(args: Args) -> Result<(), Failure> {
+    if let Some(cache) = &args.load_cache {
+        return run_app(App::from_cache(args.clone(), cache)?, &args);
+    }
+    if args.save_cache.is_some() && args.paths.len() > 1 {
+        return Err(Failure::Other("--save-cache can only save one PATH.".into()));
+    }
+    if args.watch {
+        let [path] = args.paths.as_slice() else {
+            return Err(Failure::Other("--watch can only watch one PATH.".into()));
+        };
+        if args.is_batch() || path.is_file() {
+            return watch_path(&args, path);
+        }
+    }
+    let show_headers = args.paths.len() > 1;
+    for path in &args.paths {
+        if show_headers {
+            println!("==> {} <==", path.display());
+        }
+        run_path(&args, path)?;
+    }
+    Ok(())
+}
+
+/// Print what was asked for on the command line, or start the interactive session.
+fn run_app(mut app: App, args: &Args) -> Result<(), Failure> {
+    if args.is_batch() {
+        app.run_batch()?;
+    } else {
         app.run()?;
+    }
+    Ok(())
+}
+
+/// Run the command line for `path`, then again whenever it changes, until interrupted.
+/// Errors are printed rather than stopping, since the next change might fix them.
+fn watch_path(args: &Args, path: &Path) -> Result<(), Failure> {
+    let watcher = AssetWatcher::new(path)?;
+    loop {
+        if let Err(failure) = run_path(args, path) {
+            eprintln!("Error: {}", failure);
+        }
+        watcher.wait_for_change()?;
+        eprintln!("{} changed; running again.", path.display());
+    }
+}
+
+/// Load a folder of assets into an `App`, or print the contents of a single asset file.
+fn run_path(args: &Args, path: &Path) -> Result<(), Failure> {
+    if path.is_dir() {
+        let mut app = App::new(args.clone(), path)?;
+        if let Some(cache) = &args.save_cache {
+            app.save_cache(cache)?;
+        }
+        if args.watch && !args.is_batch() {
+            app.watch(path)?;
+        }
+        run_app(app, args)
+    } else if path.is_file() {
+        print_asset_file(path, args.count_only)?;
         Ok(())
     } else {
-        Err(if args.path.is_file() {
-                "Please provide the path to the directory that contains the `.asset` files, not a file.".into()
-            } else if !args.path.try_exists()? {
-                format!("The file `{}` does not exist.", args.path.display()).into()
-            } else {
-                "An unknown error occured".into()
-            })
+        Err(Failure::Io(format!("The file `{}` does not exist.", path.display()).into()))
     }
 }
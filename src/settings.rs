@@ -0,0 +1,165 @@
+use std::{
+    io::IsTerminal,
+    sync::OnceLock,
+};
+use clap::ValueEnum;
+
+use crate::data::{ ConnectType, ConnectorOrder };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Use colour when printing to a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum PagerChoice {
+    /// Use a pager for output taller than the terminal
+    #[default]
+    Auto,
+    /// Use a pager for all long outputs, even if they fit
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum IndentStyle {
+    #[default]
+    Tabs,
+    /// Four spaces per level
+    Spaces,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConnectorStyle {
+    /// ○ △ □ ◊ 🐾 @
+    Unicode,
+    /// C T S D P @, as with the `display_compat` feature
+    Letters,
+    /// (o) /_\ [] <> paw @
+    Ascii,
+}
+
+impl Default for ConnectorStyle {
+    fn default() -> Self {
+        if cfg!(feature = "display_compat") {
+            ConnectorStyle::Letters
+        } else {
+            ConnectorStyle::Unicode
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum NoEffectLabel {
+    /// Leave it out
+    #[default]
+    Blank,
+    /// None
+    None,
+    /// -
+    Dash,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    /// The tool's interpretation of the data as YAML, not the raw Unity form
+    Yaml,
+}
+
+/// The `(width, height)` of the terminal stdout is connected to, or `None` if it isn't a terminal.
+pub fn detect_terminal_size() -> Option<(usize, usize)> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    terminal_size::terminal_size()
+        .map(|(terminal_size::Width(width), terminal_size::Height(height))| (width as usize, height as usize))
+}
+
+impl IndentStyle {
+    /// The indentation for the given nesting level.
+    pub fn indent(self, level: usize) -> String {
+        match self {
+            IndentStyle::Tabs => "\t".repeat(level),
+            IndentStyle::Spaces => "    ".repeat(level),
+        }
+    }
+
+    /// The columns taken up by the indentation for the given nesting level, counting tabs as 8.
+    pub fn width(self, level: usize) -> usize {
+        match self {
+            IndentStyle::Tabs => 8 * level,
+            IndentStyle::Spaces => 4 * level,
+        }
+    }
+}
+
+impl NoEffectLabel {
+    /// The text shown for a card without an effect.
+    pub fn text(self) -> &'static str {
+        match self {
+            NoEffectLabel::Blank => "",
+            NoEffectLabel::None => "None",
+            NoEffectLabel::Dash => "-",
+        }
+    }
+}
+
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Auto => std::io::stdout().is_terminal(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// Options chosen on the command line that change how data is parsed or displayed.
+/// These are needed deep inside `TryFrom` and `Display` impls, so they are kept globally
+/// rather than being passed around.
+#[derive(Debug, Default)]
+pub struct Settings {
+    pub strict_sequence: bool,
+    pub group_cards: bool,
+    pub color: bool,
+    pub wildcard: Option<ConnectType>,
+    /// The order to show the types in a connector, if not the default.
+    pub connector_order: Option<ConnectorOrder>,
+    /// The width of the terminal, if output is going to one.
+    pub terminal_width: Option<usize>,
+    /// The height of the terminal, if output is going to one.
+    pub terminal_height: Option<usize>,
+    pub pager: PagerChoice,
+    pub show_raw_fields: bool,
+    /// Show notes that explain the data, such as when an event becomes mad.
+    pub show_notes: bool,
+    /// Show the note on events that use their NPC's deck, saying where to find it.
+    pub show_inheritance_note: bool,
+    /// Open source files in $EDITOR when asked to show them, rather than only printing the path.
+    pub open_in_editor: bool,
+    pub indent: IndentStyle,
+    pub connector_style: ConnectorStyle,
+    pub no_effect_label: NoEffectLabel,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Set the global settings. Only the first call has any effect.
+pub fn init(settings: Settings) {
+    let _ = SETTINGS.set(settings);
+}
+
+/// The indentation for the given nesting level, in the chosen style.
+pub fn indent(level: usize) -> String {
+    settings().indent.indent(level)
+}
+
+/// The global settings, or the defaults if `init` hasn't been called.
+pub fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::default)
+}
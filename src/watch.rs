@@ -0,0 +1,67 @@
+use std::{
+    error::Error,
+    path::Path,
+    sync::mpsc::{ channel, Receiver },
+    time::Duration,
+};
+use notify::{
+    event::ModifyKind,
+    EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+
+/// How long the assets have to stay unchanged before a change is reported, so that saving several
+/// files at once, or an editor writing one in steps, only causes a single reload.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watches a folder of assets, or a single asset file, for `--watch`.
+#[derive(Debug)]
+pub struct AssetWatcher {
+    // Kept so that watching doesn't stop when it's dropped
+    _watcher: RecommendedWatcher,
+    changes: Receiver<notify::Result<notify::Event>>,
+}
+
+impl AssetWatcher {
+    pub fn new(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let (sender, changes) = channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(path, RecursiveMode::Recursive)?;
+        Ok(Self { _watcher: watcher, changes })
+    }
+
+    /// Block until something changes, then wait for the changes to settle.
+    pub fn wait_for_change(&self) -> Result<(), Box<dyn Error>> {
+        loop {
+            let event = self.changes.recv()?;
+            if is_change(event?) {
+                break;
+            }
+        }
+        self.settle();
+        Ok(())
+    }
+
+    /// Whether anything has changed since the last call, without blocking if nothing has.
+    pub fn has_changed(&self) -> bool {
+        let changed = self.changes.try_iter().any(|event| event.is_ok_and(is_change));
+        if changed {
+            self.settle();
+        }
+        changed
+    }
+
+    /// Wait until no more changes come in for `DEBOUNCE`, dropping the ones that do.
+    fn settle(&self) {
+        while self.changes.recv_timeout(DEBOUNCE).is_ok() {}
+    }
+}
+
+/// Whether an event means the contents of the assets may have changed. Reading the assets
+/// produces events too, which would otherwise cause every reload to trigger another.
+fn is_change(event: notify::Event) -> bool {
+    match event.kind {
+        EventKind::Modify(ModifyKind::Metadata(_)) => false,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => true,
+        _ => false,
+    }
+}
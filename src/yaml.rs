@@ -1,55 +1,81 @@
 use std::{
+    cmp::Ordering,
     collections::BTreeMap,
     error::Error,
     fmt::Display,
     num::TryFromIntError,
 };
+use serde::{ Deserialize, Serialize, Serializer };
 
+/// A problem reading the game's data out of a yaml asset.
 #[derive(Debug)]
-pub struct YamlError(String);
+pub enum YamlError {
+    /// A struct had no entry `key`. `entity` is the event being read, if it is known.
+    MissingField { entity: Option<String>, key: String },
+    /// The entry `key` held a different kind of value than the `expected` one.
+    WrongType { entity: Option<String>, key: String, expected: &'static str },
+    /// A number was too large for the type it is stored in.
+    Overflow(TryFromIntError),
+    /// Anything else, including yaml that isn't valid at all.
+    Parse(String),
+}
 
-impl YamlError {
-    fn new(msg: &str) -> Self {
-        Self(msg.to_owned())
+/// The prefix used to say which event an error is in, if any.
+fn entity_prefix(entity: &Option<String>) -> String {
+    match entity {
+        Some(id) => format!("event {}: ", id),
+        None => "".to_owned(),
     }
 }
 
 impl Display for YamlError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        match self {
+            YamlError::MissingField { entity, key } =>
+                write!(f, "{}Field didn't contain `{}` key.", entity_prefix(entity), key),
+            YamlError::WrongType { entity, key, expected } =>
+                write!(f, "{}Field entry `{}` is not of type {}.", entity_prefix(entity), key, expected),
+            YamlError::Overflow(err) => write!(f, "{}", err),
+            YamlError::Parse(msg) => write!(f, "{}", msg),
+        }
     }
 }
 
 impl Error for YamlError {
-    fn description(&self) -> &str {
-        &self.0
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            YamlError::Overflow(err) => Some(err),
+            _ => None,
+        }
     }
 }
 
 impl From<&str> for YamlError {
     fn from(value: &str) -> Self {
-        Self::new(value)
+        Self::Parse(value.to_owned())
     }
 }
 
 impl From<String> for YamlError {
     fn from(value: String) -> Self {
-        Self::new(&value)
+        Self::Parse(value)
     }
 }
 
-macro_rules! impl_yamlerror_from_error {
-    ($t:ty) => {
-        impl From<$t> for YamlError {
-            fn from(value: $t) -> Self {
-                Self::new(&value.to_string())
-            }
-        }
-    };
+impl From<TryFromIntError> for YamlError {
+    fn from(value: TryFromIntError) -> Self {
+        Self::Overflow(value)
+    }
 }
-impl_yamlerror_from_error!(TryFromIntError);
 
-#[derive(Debug)]
+impl From<serde_yaml::Error> for YamlError {
+    fn from(value: serde_yaml::Error) -> Self {
+        Self::Parse(value.to_string())
+    }
+}
+
+#[allow(dead_code)] // Not every kind of value is read yet, but all are kept for debug output
+#[derive(Debug, Clone)]
 pub enum Field {
     Struct(BTreeMap<String, Field>),
     List(Vec<Field>),
@@ -61,6 +87,206 @@ pub enum Field {
     Str(String),
 }
 
+impl Field {
+    pub fn as_struct(&self) -> Option<&BTreeMap<String, Field>> {
+        match self {
+            Field::Struct(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&Vec<Field>> {
+        match self {
+            Field::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_uint(&self) -> Option<u64> {
+        match self {
+            Field::Uint(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Field::Str(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// The entry `key` of a `Struct` field, or `None` if there's no such entry or the field isn't a `Struct`.
+    pub fn get(&self, key: &str) -> Option<&Field> {
+        self.as_struct()?.get(key)
+    }
+
+    /// The value of a numeric field as a float, whichever kind of number it was written as.
+    /// Unity writes small floats in scientific notation, e.g. `1.5e-05`, which are still `Float`s.
+    #[allow(dead_code)] // None of the float fields from the game are read yet
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Field::Float(v) => Some(*v),
+            Field::Uint(v) => Some(*v as f64),
+            Field::Int(v) => Some(*v as f64),
+            _ => None,
+        }
+    }
+}
+
+// Fields are ordered by kind first, in declaration order, so that cards holding them can be sorted
+impl Ord for Field {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Field::Struct(a), Field::Struct(b)) => a.cmp(b),
+            (Field::List(a), Field::List(b)) => a.cmp(b),
+            (Field::Bool(a), Field::Bool(b)) => a.cmp(b),
+            (Field::Uint(a), Field::Uint(b)) => a.cmp(b),
+            (Field::Int(a), Field::Int(b)) => a.cmp(b),
+            (Field::Float(a), Field::Float(b)) => a.total_cmp(b),
+            (Field::Null, Field::Null) => Ordering::Equal,
+            (Field::Str(a), Field::Str(b)) => a.cmp(b),
+            _ => self.kind_index().cmp(&other.kind_index()),
+        }
+    }
+}
+
+impl PartialOrd for Field {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Field {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for Field {}
+
+impl Field {
+    /// The position of the variant in the declaration, which is also its index when tagged.
+    fn kind_index(&self) -> u32 {
+        match self {
+            Field::Struct(_) => 0,
+            Field::List(_) => 1,
+            Field::Bool(_) => 2,
+            Field::Uint(_) => 3,
+            Field::Int(_) => 4,
+            Field::Float(_) => 5,
+            Field::Null => 6,
+            Field::Str(_) => 7,
+        }
+    }
+}
+
+/// `Field` as a tagged enum, which is how it is stored in formats that can't describe their own
+/// contents, such as the `--save-cache` format. Human readable formats, like yaml and json, store
+/// the value alone.
+#[derive(Deserialize)]
+#[serde(rename = "Field")]
+enum TaggedField {
+    Struct(BTreeMap<String, Field>),
+    List(Vec<Field>),
+    Bool(bool),
+    Uint(u64),
+    Int(i64),
+    Float(f64),
+    Null,
+    Str(String),
+}
+
+impl From<TaggedField> for Field {
+    fn from(value: TaggedField) -> Self {
+        match value {
+            TaggedField::Struct(v) => Field::Struct(v),
+            TaggedField::List(v) => Field::List(v),
+            TaggedField::Bool(v) => Field::Bool(v),
+            TaggedField::Uint(v) => Field::Uint(v),
+            TaggedField::Int(v) => Field::Int(v),
+            TaggedField::Float(v) => Field::Float(v),
+            TaggedField::Null => Field::Null,
+            TaggedField::Str(v) => Field::Str(v),
+        }
+    }
+}
+
+impl Serialize for Field {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            match self {
+                Field::Struct(v) => v.serialize(serializer),
+                Field::List(v) => v.serialize(serializer),
+                Field::Bool(v) => serializer.serialize_bool(*v),
+                Field::Uint(v) => serializer.serialize_u64(*v),
+                Field::Int(v) => serializer.serialize_i64(*v),
+                Field::Float(v) => serializer.serialize_f64(*v),
+                Field::Null => serializer.serialize_unit(),
+                Field::Str(v) => serializer.serialize_str(v),
+            }
+        } else {
+            let index = self.kind_index();
+            match self {
+                Field::Struct(v) => serializer.serialize_newtype_variant("Field", index, "Struct", v),
+                Field::List(v) => serializer.serialize_newtype_variant("Field", index, "List", v),
+                Field::Bool(v) => serializer.serialize_newtype_variant("Field", index, "Bool", v),
+                Field::Uint(v) => serializer.serialize_newtype_variant("Field", index, "Uint", v),
+                Field::Int(v) => serializer.serialize_newtype_variant("Field", index, "Int", v),
+                Field::Float(v) => serializer.serialize_newtype_variant("Field", index, "Float", v),
+                Field::Null => serializer.serialize_unit_variant("Field", index, "Null"),
+                Field::Str(v) => serializer.serialize_newtype_variant("Field", index, "Str", v),
+            }
+        }
+    }
+}
+
+/// The contents of a Unity `.asset` file.
+#[derive(Debug)]
+pub struct UnityAsset {
+    /// See `unity_class_id`.
+    pub class_id: Option<u64>,
+    pub root: Field,
+}
+
+impl UnityAsset {
+    pub fn parse(contents: &str) -> Result<Self, YamlError> {
+        Ok(Self {
+            class_id: unity_class_id(contents),
+            root: serde_yaml::from_str(contents)?,
+        })
+    }
+
+    /// Take the fields of the asset's `MonoBehaviour`, which is where the game's data lives.
+    pub fn into_monobehaviour(self) -> Result<BTreeMap<String, Field>, YamlError> {
+        let Field::Struct(mut data_map) = self.root else { return Err("Root isn't a map".into()); };
+        match data_map.remove("MonoBehaviour") {
+            Some(Field::Struct(monobehaviour)) => Ok(monobehaviour),
+            Some(_) => Err(YamlError::WrongType { entity: None, key: "MonoBehaviour".to_owned(), expected: "Struct" }),
+            None => Err(YamlError::MissingField { entity: None, key: "MonoBehaviour".to_owned() }),
+        }
+    }
+}
+
+/// Find the Unity class id in the `--- !u!<class id> &<file id>` header of an asset file,
+/// e.g. 114 for a MonoBehaviour.
+pub fn unity_class_id(contents: &str) -> Option<u64> {
+    contents.lines()
+        .find_map(|line| line.strip_prefix("--- !u!"))
+        .and_then(|header| header.split_whitespace().next()?.parse().ok())
+}
+
+/// The Unity class id of a `MonoBehaviour`, which is what every asset read here should be.
+pub const MONOBEHAVIOUR_CLASS_ID: u64 = 114;
+
+/// Describe a Unity class id for display, naming the classes that are expected in these assets.
+pub fn describe_class_id(class_id: Option<u64>) -> String {
+    match class_id {
+        Some(MONOBEHAVIOUR_CLASS_ID) => format!("{} (MonoBehaviour)", MONOBEHAVIOUR_CLASS_ID),
+        Some(id) => id.to_string(),
+        None => "unknown".to_owned(),
+    }
+}
+
 struct FieldVisitor;
 
 impl<'de> serde::de::Visitor<'de> for FieldVisitor {
@@ -147,70 +373,54 @@ impl<'de> serde::Deserialize<'de> for Field {
         where
             D: serde::Deserializer<'de>
     {
-        deserializer.deserialize_any(FieldVisitor)
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(FieldVisitor)
+        } else {
+            TaggedField::deserialize(deserializer).map(Field::from)
+        }
     }
 }
 
 #[macro_export]
 macro_rules! field_value_type {
-    (Uint) => { u64 };
     (Struct) => { std::collections::BTreeMap<String, Field> };
     (List) => { Vec<Field> };
-    (Bool) => { bool };
     (Uint) => { u64 };
-    (Int) => { i64 };
     (Float) => { f64 };
-    (Str) => { String };
+    (Str) => { str };
 }
 
+/// The `Field` accessor for each kind of value that is read.
 #[macro_export]
-macro_rules! field_get_body {
-    ($var:ident, $t:tt, $key:tt) => {
-            constrain_field_get_body::<_, field_value_type!($t)>(|event_id, map| {
-                let key: &str = stringify!($key);
-                let Some(field) = map.get(key.clone()) else {
-                    println!("{:?}", map);
-                    return Err(format!("{}Field didn't contain `{}` key.",
-                               if let Some(id) = event_id {
-                                   format!("event {}: ", id)
-                               } else { "".to_owned() },
-                               key).into());
-                };
-                let Field::$t($var) = field else {
-                    println!("{:?}", field);
-                    return Err(format!("{}Field entry `{}` is not of type {}.",
-                               if let Some(id) = event_id {
-                                   format!("event {}: ", id)
-                               } else { "".to_owned() },
-                               key,
-                               stringify!($t)).into());
-                };
-                Ok($var)
-            })
-    };
+macro_rules! field_accessor {
+    (Struct) => { Field::as_struct };
+    (List) => { Field::as_list };
+    (Uint) => { Field::as_uint };
+    (Float) => { Field::as_f64 };
+    (Str) => { Field::as_str };
 }
 
 #[macro_export]
 macro_rules! field_get {
     (let $var:ident: $t:tt = $map:ident.$key:tt) => {
-        let $var = {
-            let get = field_get_body!($var, $t, $key);
-            get(None, $map)
-        }?;
+        let $var = field_get_with(None, $map, stringify!($key), stringify!($t), field_accessor!($t))?;
     };
     (event $id:expr, let $var:ident: $t:tt = $map:ident.$key:tt) => {
-        let $var = {
-            let get = field_get_body!($var, $t, $key);
-            get(Some($id), $map)
-        }?;
+        let $var = field_get_with(Some($id), $map, stringify!($key), stringify!($t), field_accessor!($t))?;
     };
 }
 
-pub fn constrain_field_get_body<F, R>(f: F) -> F
-    where
-        F: for<'a> Fn(Option<&String>, &'a std::collections::BTreeMap<String, Field>) -> Result<&'a R, YamlError>
-{
-    f
+/// Read the entry `key` of `map` with `accessor`, which should be the one for the `expected` kind
+/// of value. `entity` is the event being read, if it is known.
+pub fn field_get_with<'a, R>(entity: Option<&str>, map: &'a BTreeMap<String, Field>, key: &str, expected: &'static str, accessor: fn(&'a Field) -> Option<R>) -> Result<R, YamlError> {
+    let Some(field) = map.get(key) else {
+        log::debug!("{:?}", map);
+        return Err(YamlError::MissingField { entity: entity.map(str::to_owned), key: key.to_owned() });
+    };
+    accessor(field).ok_or_else(|| {
+        log::debug!("{:?}", field);
+        YamlError::WrongType { entity: entity.map(str::to_owned), key: key.to_owned(), expected }
+    })
 }
 
 #[macro_export]
@@ -225,9 +435,99 @@ macro_rules! impl_tryfrom_field {
             type Error = YamlError;
 
             fn try_from(value: &Field) -> Result<Self, Self::Error> {
-                let Field::$ftype(inner) = value else { return Err(format!("Not a {} field", stringify!($ftype)).into()); };
-                inner.try_into()
+                let Some(inner) = field_accessor!($ftype)(value) else { return Err(format!("Not a {} field", stringify!($ftype)).into()); };
+                std::borrow::Borrow::<field_value_type!($ftype)>::borrow(&inner).try_into()
             }
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ data::{ Connector, Deck }, fixtures };
+
+    /// A map with an entry of each kind that has an accessor.
+    fn entries() -> BTreeMap<String, Field> {
+        let Field::Struct(map) = serde_yaml::from_str("{map: {a: 1}, list: [1, 2], uint: 3, float: 0.5, str: text}").unwrap() else {
+            panic!("should be a map");
+        };
+        map
+    }
+
+    #[test]
+    fn accessors_read_their_own_kind() {
+        let map = entries();
+        assert_eq!(map["map"].as_struct().map(|map| map["a"].as_uint()), Some(Some(1)));
+        assert_eq!(map["list"].as_list().map(Vec::len), Some(2));
+        assert_eq!(map["uint"].as_uint(), Some(3));
+        assert_eq!(map["float"].as_f64(), Some(0.5));
+        assert_eq!(map["str"].as_str(), Some("text"));
+        assert_eq!(map["map"].get("a").and_then(Field::as_uint), Some(1));
+    }
+
+    #[test]
+    fn accessors_reject_other_kinds() {
+        let map = entries();
+        assert!(map["list"].as_struct().is_none());
+        assert!(map["map"].as_list().is_none());
+        assert_eq!(map["str"].as_uint(), None);
+        assert_eq!(map["str"].as_f64(), None);
+        assert_eq!(map["uint"].as_str(), None);
+        assert!(map["list"].get("a").is_none());
+    }
+
+    #[test]
+    fn field_get_reads_each_kind() -> Result<(), YamlError> {
+        let map = &entries();
+        field_get!(let inner: Struct = map.map);
+        field_get!(let list: List = map.list);
+        field_get!(let uint: Uint = map.uint);
+        field_get!(let float: Float = map.float);
+        field_get!(event "ev_intro", let text: Str = map.str);
+        assert_eq!((inner.len(), list.len(), uint, float, text), (1, 2, 3, 0.5, "text"));
+        Ok(())
+    }
+
+    #[test]
+    fn field_get_names_wrong_type_and_missing_key() {
+        let map = &entries();
+        let wrong = field_get_with(Some("ev_intro"), map, "str", "Uint", Field::as_uint).unwrap_err();
+        assert!(matches!(&wrong, YamlError::WrongType { entity: Some(id), key, expected: "Uint" } if id == "ev_intro" && key == "str"), "{:?}", wrong);
+        let missing = field_get_with(None, map, "missing", "Struct", Field::as_struct).unwrap_err();
+        assert!(matches!(&missing, YamlError::MissingField { entity: None, key } if key == "missing"), "{:?}", missing);
+    }
+
+    #[test]
+    fn tryfrom_field_checks_the_kind() {
+        let map = entries();
+        assert!(Connector::try_from(&map["uint"]).is_ok());
+        assert!(Connector::try_from(&map["str"]).is_err());
+        assert!(Deck::try_from(&map["list"]).is_err());
+    }
+
+    #[test]
+    fn scientific_notation_is_a_float() {
+        let parse = |value: &str| serde_yaml::from_str::<Field>(value).unwrap();
+        assert!(matches!(parse("1e-3"), Field::Float(value) if value == 1e-3));
+        assert!(matches!(parse("2.5E+4"), Field::Float(value) if value == 2.5e4));
+        assert_eq!(parse("1.5e-05").as_f64(), Some(1.5e-5));
+        assert_eq!(parse("7").as_f64(), Some(7.0));
+        assert_eq!(parse("-7").as_f64(), Some(-7.0));
+    }
+
+    #[test]
+    fn malformed_exponent_is_a_string() {
+        let parse = |value: &str| serde_yaml::from_str::<Field>(value).unwrap();
+        assert!(matches!(parse("1.5e"), Field::Str(value) if value == "1.5e"));
+        assert!(matches!(parse("2e+"), Field::Str(value) if value == "2e+"));
+        assert_eq!(parse("1.5e").as_f64(), None);
+    }
+
+    #[test]
+    fn class_id_is_read_from_header() {
+        assert_eq!(UnityAsset::parse(fixtures::EVENT_DATA).unwrap().class_id, Some(MONOBEHAVIOUR_CLASS_ID));
+        assert_eq!(unity_class_id("%YAML 1.1\n--- !u!28 &2800000\nTexture2D: {}\n"), Some(28));
+        assert_eq!(unity_class_id("id: alice\n"), None);
+    }
+}